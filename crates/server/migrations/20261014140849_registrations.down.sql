@@ -0,0 +1 @@
+DROP TABLE registrations;
\ No newline at end of file
@@ -0,0 +1,6 @@
+CREATE TABLE registrations (
+    ip text NOT NULL,
+    registered_at integer NOT NULL DEFAULT (unixepoch())
+);
+
+CREATE INDEX registrations_ip ON registrations (ip, registered_at);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE command_history;
\ No newline at end of file
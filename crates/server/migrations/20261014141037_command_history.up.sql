@@ -0,0 +1,23 @@
+CREATE TABLE command_history (
+    user_number text NOT NULL,
+    command text NOT NULL,
+    args text NOT NULL,
+    ran_at integer NOT NULL DEFAULT (unixepoch())
+);
+
+CREATE INDEX command_history_user ON command_history (user_number);
+
+-- Only keep the 5 most recent commands for each user
+CREATE TRIGGER command_history_limit
+AFTER INSERT ON command_history
+BEGIN
+DELETE FROM command_history
+WHERE user_number = NEW.user_number
+    AND rowid NOT IN (
+        SELECT rowid
+        FROM command_history
+        WHERE user_number = NEW.user_number
+        ORDER BY rowid DESC
+        LIMIT 5
+    );
+END;
\ No newline at end of file
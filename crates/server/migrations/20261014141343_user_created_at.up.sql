@@ -0,0 +1,18 @@
+-- SQLite can't add a column with a non-constant default, so rebuild the table
+CREATE TABLE users_new (
+    number text PRIMARY KEY NOT NULL,
+    name text NOT NULL,
+    locale text NOT NULL DEFAULT 'en',
+    created_at integer NOT NULL DEFAULT (unixepoch())
+);
+
+INSERT INTO users_new (number, name, locale)
+SELECT number,
+    name,
+    locale
+FROM users;
+
+DROP TABLE users;
+
+ALTER TABLE users_new
+    RENAME TO users;
\ No newline at end of file
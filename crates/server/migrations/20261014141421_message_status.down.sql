@@ -0,0 +1 @@
+DROP TABLE message_status;
\ No newline at end of file
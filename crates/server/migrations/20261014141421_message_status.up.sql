@@ -0,0 +1,6 @@
+CREATE TABLE message_status (
+    message_sid text PRIMARY KEY NOT NULL,
+    status text NOT NULL,
+    error_code text,
+    updated_at integer NOT NULL DEFAULT (unixepoch())
+);
\ No newline at end of file
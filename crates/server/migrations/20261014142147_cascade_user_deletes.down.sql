@@ -0,0 +1,35 @@
+CREATE TABLE command_history_old (
+    user_number text NOT NULL,
+    command text NOT NULL,
+    args text NOT NULL,
+    ran_at integer NOT NULL DEFAULT (unixepoch())
+);
+
+INSERT INTO command_history_old (user_number, command, args, ran_at)
+SELECT user_number,
+    command,
+    args,
+    ran_at
+FROM command_history
+ORDER BY rowid;
+
+DROP TABLE command_history;
+
+ALTER TABLE command_history_old
+    RENAME TO command_history;
+
+CREATE INDEX command_history_user ON command_history (user_number);
+
+CREATE TRIGGER command_history_limit
+AFTER INSERT ON command_history
+BEGIN
+DELETE FROM command_history
+WHERE user_number = NEW.user_number
+    AND rowid NOT IN (
+        SELECT rowid
+        FROM command_history
+        WHERE user_number = NEW.user_number
+        ORDER BY rowid DESC
+        LIMIT 5
+    );
+END;
\ No newline at end of file
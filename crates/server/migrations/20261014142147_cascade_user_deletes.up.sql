@@ -0,0 +1,41 @@
+-- SQLite can't add a foreign key to an existing table, so rebuild it
+CREATE TABLE command_history_new (
+    user_number text NOT NULL REFERENCES users (number) ON DELETE CASCADE,
+    command text NOT NULL,
+    args text NOT NULL,
+    ran_at integer NOT NULL DEFAULT (unixepoch())
+);
+
+INSERT INTO command_history_new (user_number, command, args, ran_at)
+SELECT user_number,
+    command,
+    args,
+    ran_at
+FROM command_history
+WHERE user_number IN (
+        SELECT number
+        FROM users
+    )
+ORDER BY rowid;
+
+DROP TABLE command_history;
+
+ALTER TABLE command_history_new
+    RENAME TO command_history;
+
+CREATE INDEX command_history_user ON command_history (user_number);
+
+-- Only keep the 5 most recent commands for each user
+CREATE TRIGGER command_history_limit
+AFTER INSERT ON command_history
+BEGIN
+DELETE FROM command_history
+WHERE user_number = NEW.user_number
+    AND rowid NOT IN (
+        SELECT rowid
+        FROM command_history
+        WHERE user_number = NEW.user_number
+        ORDER BY rowid DESC
+        LIMIT 5
+    );
+END;
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP INDEX users_last_active;
+
+ALTER TABLE users DROP COLUMN last_active;
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- SQLite can't add a column with a non-constant default, and rebuilding users
+-- would cascade into command_history, so new users set last_active on insert
+ALTER TABLE users
+ADD COLUMN last_active integer NOT NULL DEFAULT 0;
+
+UPDATE users
+SET last_active = created_at;
+
+CREATE INDEX users_last_active ON users (last_active);
\ No newline at end of file
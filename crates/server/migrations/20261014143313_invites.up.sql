@@ -0,0 +1,9 @@
+CREATE TABLE invites (
+    inviter_number text NOT NULL REFERENCES users (number) ON DELETE CASCADE,
+    invitee_number text NOT NULL,
+    sent_at integer NOT NULL DEFAULT (unixepoch()),
+    -- Set when the invitee signs up
+    registered_at integer
+);
+
+CREATE INDEX invites_invitee ON invites (invitee_number, sent_at);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE command_stats;
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE command_stats (
+    command text NOT NULL,
+    -- "ok" or "error"
+    outcome text NOT NULL,
+    count integer NOT NULL DEFAULT 0,
+    PRIMARY KEY (command, outcome)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE number_types;
\ No newline at end of file
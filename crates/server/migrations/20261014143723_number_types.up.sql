@@ -0,0 +1,7 @@
+-- Cached Twilio Lookup results
+CREATE TABLE number_types (
+    number text PRIMARY KEY NOT NULL,
+    -- e.g. "mobile", "landline" or "voip"; NULL if Twilio didn't say
+    type text,
+    looked_up_at integer NOT NULL DEFAULT (unixepoch())
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS invites_inviter;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Used when deleting a user, both directly and through the cascade
+CREATE INDEX IF NOT EXISTS invites_inviter ON invites (inviter_number);
\ No newline at end of file
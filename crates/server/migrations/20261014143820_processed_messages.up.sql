@@ -0,0 +1,9 @@
+-- Webhooks already handled, so Twilio's retries get the original reply
+CREATE TABLE processed_messages (
+    message_sid text PRIMARY KEY NOT NULL,
+    -- NULL until the first attempt finishes
+    response text,
+    processed_at integer NOT NULL DEFAULT (unixepoch())
+);
+
+CREATE INDEX processed_messages_processed_at ON processed_messages (processed_at);
\ No newline at end of file
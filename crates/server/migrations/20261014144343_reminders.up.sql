@@ -0,0 +1,11 @@
+CREATE TABLE reminders (
+    id integer PRIMARY KEY,
+    user_number text NOT NULL REFERENCES users (number) ON DELETE CASCADE,
+    message text NOT NULL,
+    due_at integer NOT NULL,
+    sent boolean NOT NULL DEFAULT 0
+);
+
+CREATE INDEX reminders_due ON reminders (sent, due_at);
+
+CREATE INDEX reminders_user ON reminders (user_number);
\ No newline at end of file
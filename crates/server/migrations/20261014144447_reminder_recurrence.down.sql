@@ -0,0 +1 @@
+ALTER TABLE reminders DROP COLUMN recurrence;
\ No newline at end of file
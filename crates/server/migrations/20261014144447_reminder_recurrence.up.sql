@@ -0,0 +1,3 @@
+-- NULL for one-off reminders
+ALTER TABLE reminders
+ADD COLUMN recurrence text CHECK (recurrence IN ('daily', 'weekly', 'monthly'));
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE users
+ADD COLUMN timezone text NOT NULL DEFAULT 'UTC';
\ No newline at end of file
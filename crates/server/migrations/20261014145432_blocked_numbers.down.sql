@@ -0,0 +1 @@
+DROP TABLE blocked_numbers;
\ No newline at end of file
@@ -0,0 +1,5 @@
+-- Numbers that texted an opt-out keyword like STOP; nothing they send is processed until START, YES or UNSTOP
+CREATE TABLE blocked_numbers (
+    number text PRIMARY KEY NOT NULL,
+    blocked_at integer NOT NULL DEFAULT (unixepoch())
+);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE processed_messages DROP COLUMN from_number;
\ No newline at end of file
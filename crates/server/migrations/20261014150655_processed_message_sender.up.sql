@@ -0,0 +1,3 @@
+-- Who sent it, so deleting a user also forgets replies that quoted their data
+ALTER TABLE processed_messages
+ADD COLUMN from_number text;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE reminders DROP COLUMN claimed_at;
\ No newline at end of file
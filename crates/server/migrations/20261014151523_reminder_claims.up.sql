@@ -0,0 +1,3 @@
+-- Set while a reminder is being sent, so it isn't sent twice; cleared once the send is done
+ALTER TABLE reminders
+ADD COLUMN claimed_at integer;
\ No newline at end of file
@@ -0,0 +1,70 @@
+use std::{collections::HashMap, env};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Per-number behavior, so one server can answer several Twilio numbers
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct BotConfig {
+    pub number: String,
+    pub name: String,
+    // Without one, the welcome from the locale catalog is used
+    #[serde(default)]
+    pub welcome_message: Option<String>,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            number: String::new(),
+            name: "Laconic".to_string(),
+            welcome_message: None,
+        }
+    }
+}
+
+pub(crate) type Bots = HashMap<String, BotConfig>;
+
+// BOTS is a JSON array of bot configs, e.g.
+// [{"number": "+15551234567", "name": "Personal Bot", "welcome_message": "Hi!"}]
+pub(crate) fn load_bots() -> Result<Bots> {
+    let Ok(json) = env::var("BOTS") else {
+        return Ok(Bots::new());
+    };
+    parse_bots(&json)
+}
+
+fn parse_bots(json: &str) -> Result<Bots> {
+    let bots: Vec<BotConfig> = serde_json::from_str(json).context("While parsing BOTS")?;
+    Ok(bots
+        .into_iter()
+        .map(|bot| (bot.number.clone(), bot))
+        .collect())
+}
+
+pub(crate) fn bot_for(bots: &Bots, to: &str) -> BotConfig {
+    bots.get(to).cloned().unwrap_or_default()
+}
+
+#[test]
+fn bots() {
+    let bots = parse_bots(
+        r#"[{"number": "+15551234567", "name": "Personal Bot", "welcome_message": "Hi!"}]"#,
+    )
+    .unwrap();
+    assert_eq!(bots.len(), 1);
+    assert_eq!(bots["+15551234567"].name, "Personal Bot");
+    assert!(parse_bots("[]").unwrap().is_empty());
+    assert!(parse_bots("not json").is_err());
+    assert!(parse_bots(r#"[{"number": "+15551234567"}]"#).is_err());
+
+    assert_eq!(
+        bot_for(&bots, "+15551234567").welcome_message.as_deref(),
+        Some("Hi!")
+    );
+    assert_eq!(bot_for(&bots, "+15559999999").name, "Laconic");
+
+    // The welcome is optional, and falls back to the translated default
+    let bots = parse_bots(r#"[{"number": "+15551234567", "name": "Personal Bot"}]"#).unwrap();
+    assert_eq!(bots["+15551234567"].welcome_message, None);
+}
@@ -0,0 +1,143 @@
+/// Twilio rejects messages over 1600 characters, so nothing longer than this is sent
+pub(crate) const MAX_SEGMENT_LENGTH: usize = 1550;
+/// Leaves room for the part numbers, so numbered parts never need splitting again
+pub(crate) const MAX_CHUNK_LEN: usize = MAX_SEGMENT_LENGTH - "(99/99) ".len();
+const CONTINUED: &str = "(cont.) ";
+
+/// Splits a message into numbered parts of at most `max_len` characters (plus the
+/// "(1/N) " prefix), breaking at newlines where possible, otherwise between words.
+/// Only a single word longer than `max_len` is ever split.
+pub(crate) fn split_message(message: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = message.trim();
+    while rest.chars().count() > max_len {
+        // byte range of the first max_len + 1 characters, so that a break
+        // right after the first max_len characters is considered
+        let (limit, c) = rest.char_indices().nth(max_len).unwrap();
+        let window = &rest[..limit + c.len_utf8()];
+        let split = window
+            .rfind('\n')
+            .or_else(|| window.rfind(char::is_whitespace))
+            .filter(|&split| split > 0)
+            .unwrap_or(limit);
+        chunks.push(rest[..split].trim_end().to_string());
+        rest = rest[split..].trim_start();
+    }
+    chunks.push(rest.to_string());
+    if chunks.len() == 1 {
+        return chunks;
+    }
+    let count = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("({}/{count}) {chunk}", i + 1))
+        .collect()
+}
+
+/// Splits a message into segments of at most MAX_SEGMENT_LENGTH characters, breaking
+/// at the last newline before the limit where there is one. All but the first segment
+/// start with "(cont.) ", counted within the limit.
+pub(crate) fn split_segments(message: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut rest = message;
+    loop {
+        let prefix = if segments.is_empty() { "" } else { CONTINUED };
+        let max_len = MAX_SEGMENT_LENGTH - prefix.len();
+        let Some((limit, c)) = rest.char_indices().nth(max_len) else {
+            segments.push(format!("{prefix}{rest}"));
+            return segments;
+        };
+        let window = &rest[..limit + c.len_utf8()];
+        let split = window
+            .rfind('\n')
+            .filter(|&split| split > 0)
+            .unwrap_or(limit);
+        segments.push(format!("{prefix}{}", &rest[..split]));
+        rest = rest[split..].strip_prefix('\n').unwrap_or(&rest[split..]);
+        // The newline was the last thing that didn't fit
+        if rest.is_empty() {
+            return segments;
+        }
+    }
+}
+
+#[test]
+fn under_limit() {
+    assert_eq!(split_message("hello there", 20), vec!["hello there"]);
+}
+
+#[test]
+fn exact_boundary() {
+    let message = "a".repeat(MAX_CHUNK_LEN);
+    assert_eq!(
+        split_message(&message, MAX_CHUNK_LEN),
+        vec![message.clone()]
+    );
+    let message = format!("{message} b");
+    assert_eq!(
+        split_message(&message, MAX_CHUNK_LEN),
+        vec![
+            format!("(1/2) {}", "a".repeat(MAX_CHUNK_LEN)),
+            "(2/2) b".to_string()
+        ]
+    );
+}
+
+#[test]
+fn multiple_chunks() {
+    assert_eq!(
+        split_message("one two three four five", 9),
+        vec!["(1/3) one two", "(2/3) three", "(3/3) four five"]
+    );
+    // newlines are preferred over spaces
+    assert_eq!(
+        split_message("one\ntwo three", 10),
+        vec!["(1/2) one", "(2/2) two three"]
+    );
+    // words longer than the limit have to be split
+    assert_eq!(split_message("abcdef", 4), vec!["(1/2) abcd", "(2/2) ef"]);
+    let message = "word ".repeat(1000);
+    let chunks = split_message(&message, MAX_CHUNK_LEN);
+    assert_eq!(chunks.len(), 4);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let (prefix, text) = chunk.split_once(' ').unwrap();
+        assert_eq!(prefix, format!("({}/4)", i + 1));
+        assert!(text.chars().count() <= MAX_CHUNK_LEN);
+        assert!(text.split(' ').all(|word| word == "word"));
+    }
+}
+
+#[test]
+fn segments() {
+    let message = "a".repeat(MAX_SEGMENT_LENGTH);
+    assert_eq!(split_segments(&message), vec![message.clone()]);
+    assert_eq!(
+        split_segments(&"a".repeat(MAX_SEGMENT_LENGTH + 1)),
+        vec![message.clone(), "(cont.) a".to_string()]
+    );
+    let continued = MAX_SEGMENT_LENGTH - CONTINUED.len();
+    assert_eq!(
+        split_segments(&"a".repeat(MAX_SEGMENT_LENGTH * 2)),
+        vec![
+            message.clone(),
+            format!("(cont.) {}", "a".repeat(continued)),
+            format!("(cont.) {}", "a".repeat(MAX_SEGMENT_LENGTH - continued)),
+        ]
+    );
+    // Broken at the newline rather than the limit
+    let (first, second) = ("a".repeat(1000), "b".repeat(1000));
+    assert_eq!(
+        split_segments(&format!("{first}\n{second}")),
+        vec![first, format!("(cont.) {second}")]
+    );
+    assert_eq!(
+        split_segments(&format!("{message}\n")),
+        vec![message.clone()]
+    );
+    // Numbered parts always fit in one segment
+    let message = "word ".repeat(10000);
+    for chunk in split_message(&message, MAX_CHUNK_LEN) {
+        assert_eq!(split_segments(&chunk), vec![chunk.clone()]);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::info;
+
+// Consecutive failures before we stop calling Twilio
+const FAILURE_THRESHOLD: u32 = 5;
+// How long to wait before letting a probe through
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+pub(crate) type SharedTwilioCircuit = Arc<Mutex<TwilioCircuit>>;
+
+#[derive(Debug)]
+enum State {
+    Closed { failures: u32 },
+    Open { since: Instant },
+    // A single probe is in flight
+    HalfOpen,
+}
+
+/// Circuit breaker that stops calls to Twilio while it keeps failing
+#[derive(Debug)]
+pub(crate) struct TwilioCircuit {
+    state: State,
+}
+
+impl Default for TwilioCircuit {
+    fn default() -> Self {
+        Self {
+            state: State::Closed { failures: 0 },
+        }
+    }
+}
+
+impl TwilioCircuit {
+    /// Whether a call may go ahead; the caller must report how it went
+    pub fn allow(&mut self) -> bool {
+        self.allow_at(Instant::now())
+    }
+
+    fn allow_at(&mut self, now: Instant) -> bool {
+        match self.state {
+            State::Closed { .. } => true,
+            State::Open { since } if now.duration_since(since) >= OPEN_DURATION => {
+                info!("Twilio circuit half-open, sending a probe");
+                self.state = State::HalfOpen;
+                true
+            }
+            State::Open { .. } | State::HalfOpen => false,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        if !matches!(self.state, State::Closed { .. }) {
+            info!("Twilio circuit closed");
+        }
+        self.state = State::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&mut self) {
+        self.record_failure_at(Instant::now());
+    }
+
+    fn record_failure_at(&mut self, now: Instant) {
+        match self.state {
+            State::Closed { failures } if failures + 1 < FAILURE_THRESHOLD => {
+                self.state = State::Closed {
+                    failures: failures + 1,
+                };
+            }
+            State::Closed { .. } => {
+                info!("Twilio circuit open after {FAILURE_THRESHOLD} consecutive failures");
+                self.state = State::Open { since: now };
+            }
+            State::HalfOpen => {
+                info!("Twilio circuit probe failed, reopening");
+                self.state = State::Open { since: now };
+            }
+            State::Open { .. } => {}
+        }
+    }
+}
+
+#[test]
+fn circuit() {
+    let mut circuit = TwilioCircuit::default();
+    let start = Instant::now();
+    for _ in 0..FAILURE_THRESHOLD - 1 {
+        assert!(circuit.allow_at(start));
+        circuit.record_failure_at(start);
+    }
+    // A success resets the count
+    circuit.record_success();
+    for _ in 0..FAILURE_THRESHOLD {
+        assert!(circuit.allow_at(start));
+        circuit.record_failure_at(start);
+    }
+    assert!(!circuit.allow_at(start));
+    let later = start + OPEN_DURATION;
+    assert!(circuit.allow_at(later));
+    // Only one probe at a time
+    assert!(!circuit.allow_at(later));
+    circuit.record_failure_at(later);
+    assert!(!circuit.allow_at(later));
+    let even_later = later + OPEN_DURATION;
+    assert!(circuit.allow_at(even_later));
+    circuit.record_success();
+    assert!(circuit.allow_at(even_later));
+}
@@ -0,0 +1,40 @@
+use std::net::IpAddr;
+
+use axum::{extract::Request, http::HeaderMap, middleware::Next, response::Response};
+
+/// Address of the client that originally sent the request, as reported by the reverse proxy
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ClientIp(pub Option<IpAddr>);
+
+pub(crate) async fn extract_client_ip(mut request: Request, next: Next) -> Response {
+    let ip = forwarded_for(request.headers());
+    request.extensions_mut().insert(ClientIp(ip));
+    next.run(request).await
+}
+
+// Only the rightmost X-Forwarded-For entry is trustworthy, since our own proxy appended it.
+// Everything to its left came from the sender, who can put anything there.
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get("x-forwarded-for")?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next_back()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[test]
+fn client_ip() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(forwarded_for(&headers), None);
+    headers.insert("x-forwarded-for", "10.0.0.1, 203.0.113.7".parse().unwrap());
+    assert_eq!(
+        forwarded_for(&headers),
+        Some("203.0.113.7".parse().unwrap())
+    );
+    headers.insert("x-forwarded-for", "garbage".parse().unwrap());
+    assert_eq!(forwarded_for(&headers), None);
+}
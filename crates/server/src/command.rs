@@ -3,20 +3,51 @@ use std::fmt::Display;
 use enum_iterator::Sequence;
 use serde::{Deserialize, Serialize};
 
+use crate::i18n::MessageCatalog;
+
 // variants must be all lowercase for serde_json to deserialize them
+// variants must not carry data, so that Command stays Copy
 #[allow(non_camel_case_types)]
-#[derive(Deserialize, Serialize, Sequence, Debug)]
+#[derive(Deserialize, Serialize, Sequence, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum Command {
     h,
     name,
     info,
     stop,
+    lang,
+    tz,
+    status,
+    profile,
+    mydata,
+    invite,
+    remind,
+    reminders,
+    // Not "cancel", which Twilio treats as an opt-out
+    unremind,
+    stats,
+    debug,
 }
 
+// Alternative words (lowercase) that map to a command
+const ALIASES: &[(&str, Command)] = &[
+    ("?", Command::h),
+    ("help", Command::h),
+    ("commands", Command::h),
+    ("quit", Command::stop),
+    ("language", Command::lang),
+    ("timezone", Command::tz),
+    ("history", Command::status),
+    ("me", Command::profile),
+];
+
 impl TryFrom<&str> for Command {
     type Error = serde_json::Error;
     fn try_from(value: &str) -> std::prelude::v1::Result<Self, Self::Error> {
-        serde_json::from_str(&format!("\"{}\"", value.to_lowercase()))
+        let value = value.to_lowercase();
+        if let Some((_, command)) = ALIASES.iter().find(|(alias, _)| *alias == value) {
+            return Ok(*command);
+        }
+        serde_json::from_str(&format!("\"{value}\""))
     }
 }
 
@@ -26,54 +57,52 @@ impl Display for Command {
     }
 }
 
-struct ParameterDoc {
-    example: String,
-    description: String,
-}
-
 impl Command {
-    pub fn description(&self) -> String {
-        match self {
-            Self::h => "show a list of available commands",
-            Self::info => "see information about a command",
-            Self::name => "set your preferred name",
-            Self::stop => "stop receiving messages and remove yourself from the database",
-        }
-        .to_string()
+    pub fn description(&self, msg: &MessageCatalog) -> String {
+        msg.command_description(*self)
     }
-    fn parameter_doc(&self) -> Option<ParameterDoc> {
+    // Examples aren't translated, since they have to be valid input
+    fn parameter_example(&self) -> Option<String> {
         match self {
             Self::h => None,
-            Self::info => Some(ParameterDoc {
-                example: Command::name.to_string(),
-                description: "a command".to_string(),
-            }),
-            Self::name => Some(ParameterDoc {
-                example: "John S.".to_string(),
-                description: "your name".to_string(),
-            }),
+            Self::info => Some(Command::name.to_string()),
+            Self::name => Some("John S.".to_string()),
             Self::stop => None,
+            Self::lang => Some("es".to_string()),
+            Self::tz => Some("America/New_York".to_string()),
+            Self::status => None,
+            Self::profile => None,
+            Self::mydata => None,
+            Self::invite => Some("+15551234567".to_string()),
+            // Also "daily at 9am stretch", or just "weekly water the plants"
+            Self::remind => Some("at 3pm tomorrow call Alex".to_string()),
+            Self::reminders => None,
+            Self::unremind => Some("3".to_string()),
+            Self::stats => None,
+            Self::debug => None,
         }
     }
-    pub fn usage(&self) -> String {
-        if let Some(ParameterDoc { description, .. }) = self.parameter_doc() {
-            format!("Reply \"{self} X\", where X is {description}")
+    pub fn is_admin_only(&self) -> bool {
+        matches!(self, Self::stats | Self::debug)
+    }
+    pub fn usage(&self, msg: &MessageCatalog) -> String {
+        if self.parameter_example().is_some() {
+            msg.usage_with_parameter(*self)
         } else {
-            format!("Reply \"{self}\"")
+            msg.usage(*self)
         }
     }
-    pub fn example(&self) -> String {
-        self.parameter_doc()
-            .map(|ParameterDoc { example, .. }| format!("\nExample: \"{self} {example}\""))
+    pub fn example(&self, msg: &MessageCatalog) -> String {
+        self.parameter_example()
+            .map(|example| format!("\n{}", msg.example(*self, &example)))
             .unwrap_or_default()
     }
-    pub fn hint(&self) -> String {
-        format!(
-            "{}, to {}.{}",
-            self.usage(),
-            self.description(),
-            self.example()
-        )
+    pub fn hint(&self, msg: &MessageCatalog) -> String {
+        msg.hint(&self.usage(msg), &self.description(msg), &self.example(msg))
+    }
+    // The longer form "info" gives
+    pub fn info(&self, msg: &MessageCatalog) -> String {
+        msg.command_info(&self.usage(msg), &self.description(msg), &self.example(msg))
     }
 }
 
@@ -84,4 +113,34 @@ fn command() {
         Command::try_from(command_text).unwrap().to_string(),
         command_text
     );
+    assert_eq!(Command::try_from("?").unwrap(), Command::h);
+    assert_eq!(Command::try_from("help").unwrap(), Command::h);
+    assert_eq!(Command::try_from("HELP").unwrap(), Command::h);
+    assert_eq!(Command::try_from("commands").unwrap(), Command::h);
+    assert_eq!(Command::try_from("quit").unwrap(), Command::stop);
+    assert_eq!(Command::try_from("language").unwrap(), Command::lang);
+    assert_eq!(Command::try_from("timezone").unwrap(), Command::tz);
+    assert_eq!(Command::try_from("history").unwrap(), Command::status);
+    assert_eq!(Command::try_from("me").unwrap(), Command::profile);
+    assert!(Command::try_from("x").is_err());
+}
+
+#[test]
+fn command_docs() {
+    let msg = MessageCatalog::new(crate::i18n::DEFAULT_LOCALE);
+    for command in enum_iterator::all::<Command>() {
+        assert!(
+            !command.description(&msg).is_empty(),
+            "{command} has no description"
+        );
+        assert!(!command.usage(&msg).is_empty(), "{command} has no usage");
+        assert!(
+            command.hint(&msg).contains(&command.to_string()),
+            "{command} hint does not mention the command"
+        );
+    }
+    assert_eq!(
+        Command::name.hint(&msg),
+        "Reply \"name X\", where X is your name, to set your preferred name.\nExample: \"name John S.\""
+    );
 }
@@ -0,0 +1,70 @@
+use std::{str::FromStr, time::Duration};
+
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    Pool, Sqlite,
+};
+use tracing::info;
+
+use crate::error::env_number;
+
+// DATABASE_POOL_SIZE (default 5), DATABASE_POOL_MIN_SIZE, and the
+// DATABASE_ACQUIRE_TIMEOUT_SECS and DATABASE_IDLE_TIMEOUT_SECS timeouts
+fn pool_options() -> SqlitePoolOptions {
+    let mut options =
+        SqlitePoolOptions::new().max_connections(env_number("DATABASE_POOL_SIZE").unwrap_or(5));
+    if let Some(min) = env_number("DATABASE_POOL_MIN_SIZE") {
+        options = options.min_connections(min);
+    }
+    if let Some(secs) = env_number("DATABASE_ACQUIRE_TIMEOUT_SECS") {
+        options = options.acquire_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = env_number("DATABASE_IDLE_TIMEOUT_SECS") {
+        options = options.idle_timeout(Duration::from_secs(secs));
+    }
+    options
+}
+
+// WAL lets readers proceed during writes, and the busy timeout makes
+// concurrent writers wait for the lock instead of failing with SQLITE_BUSY.
+// NORMAL synchronous is safe with WAL and avoids an fsync per transaction.
+// The pool applies these options to every connection it opens.
+pub(crate) async fn connect(url: &str) -> sqlx::Result<Pool<Sqlite>> {
+    let options = SqliteConnectOptions::from_str(url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(Duration::from_secs(5))
+        .synchronous(SqliteSynchronous::Normal);
+    let pool_options = pool_options();
+    info!(
+        "Database pool: max_connections={}, min_connections={}, acquire_timeout={:?}, idle_timeout={:?}",
+        pool_options.get_max_connections(),
+        pool_options.get_min_connections(),
+        pool_options.get_acquire_timeout(),
+        pool_options.get_idle_timeout()
+    );
+    pool_options.connect_with(options).await
+}
+
+#[tokio::test]
+async fn pragmas() -> sqlx::Result<()> {
+    let path = std::env::temp_dir().join(format!("pragmas-{}.sqlite3", std::process::id()));
+    let pool = connect(&format!("sqlite:{}?mode=rwc", path.display())).await?;
+    let journal_mode: String = sqlx::query_scalar("pragma journal_mode")
+        .fetch_one(&pool)
+        .await?;
+    let busy_timeout: i64 = sqlx::query_scalar("pragma busy_timeout")
+        .fetch_one(&pool)
+        .await?;
+    let synchronous: i64 = sqlx::query_scalar("pragma synchronous")
+        .fetch_one(&pool)
+        .await?;
+    pool.close().await;
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+    assert_eq!(journal_mode, "wal");
+    assert_eq!(busy_timeout, 5000);
+    // NORMAL
+    assert_eq!(synchronous, 1);
+    Ok(())
+}
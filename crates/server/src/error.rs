@@ -0,0 +1,97 @@
+use std::{env, str::FromStr};
+
+use openapi::apis::{api20100401_message_api::CreateMessageError, Error as ApiError};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Errors from handling a command
+#[derive(Error, Debug)]
+pub(crate) enum BotError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Twilio error while sending message: {0}")]
+    Twilio(#[from] SendError),
+    #[error("Twilio error while looking up number: {0}")]
+    Lookup(#[from] reqwest::Error),
+    #[error("Missing environment variable {0}")]
+    Config(&'static str),
+    // The message is shown to the user as-is
+    #[error("{0}")]
+    Validation(String),
+}
+
+/// Errors from sending a message, split by whether trying again could help
+#[derive(Error, Debug)]
+pub(crate) enum SendError {
+    #[error("permanent failure: {0}")]
+    Permanent(ApiError<CreateMessageError>),
+    #[error("transient failure: {0}")]
+    Transient(ApiError<CreateMessageError>),
+    // Twilio has been failing, so we didn't try
+    #[error("circuit breaker is open")]
+    Unavailable,
+}
+
+// Twilio error codes for rate limiting and its own outages
+const TRANSIENT_ERROR_CODES: &[u32] = &[20429, 20500, 20503];
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: Option<u32>,
+}
+
+impl From<ApiError<CreateMessageError>> for SendError {
+    fn from(error: ApiError<CreateMessageError>) -> Self {
+        let transient = match &error {
+            ApiError::ResponseError(response) => {
+                let code = serde_json::from_str::<ErrorBody>(&response.content)
+                    .ok()
+                    .and_then(|body| body.code);
+                response.status.as_u16() == 429
+                    || response.status.is_server_error()
+                    || code.is_some_and(|code| TRANSIENT_ERROR_CODES.contains(&code))
+            }
+            // Couldn't reach Twilio
+            ApiError::Reqwest(_) | ApiError::Io(_) => true,
+            // Twilio answered, so the message may well have gone out
+            ApiError::Serde(_) => false,
+        };
+        if transient {
+            Self::Transient(error)
+        } else {
+            Self::Permanent(error)
+        }
+    }
+}
+
+pub(crate) fn env_var(name: &'static str) -> Result<String, BotError> {
+    env::var(name).map_err(|_| BotError::Config(name))
+}
+
+// None when the variable is unset or doesn't parse, so callers can pick a default
+pub(crate) fn env_number<T: FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok().and_then(|value| value.parse().ok())
+}
+
+#[test]
+fn send_errors() {
+    use openapi::apis::ResponseContent;
+    let response = |status: u16, content: &str| {
+        SendError::from(ApiError::ResponseError(ResponseContent {
+            status: reqwest::StatusCode::from_u16(status).unwrap(),
+            content: content.to_string(),
+            entity: None,
+        }))
+    };
+    assert!(matches!(response(429, ""), SendError::Transient(_)));
+    assert!(matches!(response(503, ""), SendError::Transient(_)));
+    assert!(matches!(
+        response(400, r#"{"code": 20429}"#),
+        SendError::Transient(_)
+    ));
+    // Invalid 'To' number
+    assert!(matches!(
+        response(400, r#"{"code": 21211}"#),
+        SendError::Permanent(_)
+    ));
+}
@@ -0,0 +1,79 @@
+use std::{env, time::Duration};
+
+use axum::{http::StatusCode, Extension, Json};
+use serde_json::{json, Value};
+use sqlx::query;
+
+use crate::state::SharedState;
+
+const REQUIRED_ENV_VARS: &[&str] = &[
+    "TWILIO_ACCOUNT_SID",
+    "TWILIO_API_KEY_SID",
+    "TWILIO_API_KEY_SECRET",
+    "TWILIO_AUTH_TOKEN",
+    "SERVER_NUMBER",
+    "CLIENT_NUMBER",
+    "DATABASE_URL",
+];
+
+type HealthResponse = (StatusCode, Json<Value>);
+
+fn ok() -> HealthResponse {
+    (StatusCode::OK, Json(json!({"status": "ok"})))
+}
+
+fn unavailable(detail: String) -> HealthResponse {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({"status": "error", "detail": detail})),
+    )
+}
+
+// Liveness check: the server is up and can reach its database
+pub(crate) async fn handle_health(Extension(state): Extension<SharedState>) -> HealthResponse {
+    match query!("select 1 as one").fetch_one(&state.pool).await {
+        Ok(_) => ok(),
+        Err(error) => unavailable(error.to_string()),
+    }
+}
+
+// Readiness check: also verifies configuration and that Twilio is reachable
+pub(crate) async fn handle_ready(Extension(state): Extension<SharedState>) -> HealthResponse {
+    let health = handle_health(Extension(state)).await;
+    if health.0 != StatusCode::OK {
+        return health;
+    }
+    let missing = REQUIRED_ENV_VARS
+        .iter()
+        .filter(|name| env::var(name).is_err())
+        .copied()
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return unavailable(format!(
+            "Missing environment variables: {}",
+            missing.join(", ")
+        ));
+    }
+    let twilio = reqwest::Client::new()
+        .head("https://api.twilio.com/")
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await;
+    match twilio {
+        Ok(_) => ok(),
+        Err(error) => unavailable(format!("Twilio unreachable: {error}")),
+    }
+}
+
+#[sqlx::test]
+async fn health(pool: sqlx::Pool<sqlx::Sqlite>) {
+    let state = std::sync::Arc::new(crate::state::AppState::test(pool.clone()));
+    assert_eq!(
+        handle_health(Extension(state.clone())).await.0,
+        StatusCode::OK
+    );
+    pool.close().await;
+    let (status, Json(body)) = handle_health(Extension(state)).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "error");
+}
@@ -0,0 +1,398 @@
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use crate::{command::Command, reminders::Recurrence};
+
+pub(crate) const DEFAULT_LOCALE: &str = "en";
+
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.toml")),
+    ("es", include_str!("../locales/es.toml")),
+];
+
+type Messages = HashMap<String, String>;
+
+fn catalogs() -> &'static HashMap<&'static str, Messages> {
+    static CATALOGS: OnceLock<HashMap<&'static str, Messages>> = OnceLock::new();
+    CATALOGS.get_or_init(|| {
+        LOCALES
+            .iter()
+            .map(|(locale, source)| {
+                let messages = toml::from_str(source)
+                    .unwrap_or_else(|error| panic!("Invalid {locale} locale file: {error}"));
+                (*locale, messages)
+            })
+            .collect()
+    })
+}
+
+/// Everything mydata exports, already formatted for display
+pub(crate) struct UserData<'a> {
+    pub number: &'a str,
+    pub name: &'a str,
+    pub locale: &'a str,
+    pub timezone: &'a str,
+    pub registered: &'a str,
+    pub last_active: &'a str,
+    pub commands: &'a [String],
+    pub reminders: &'a [String],
+    pub invites: &'a [String],
+}
+
+pub(crate) fn is_supported(locale: &str) -> bool {
+    LOCALES.iter().any(|(supported, _)| *supported == locale)
+}
+
+pub(crate) fn supported_locales() -> String {
+    LOCALES
+        .iter()
+        .map(|(locale, _)| *locale)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// User-facing bot responses in a single language
+pub(crate) struct MessageCatalog {
+    messages: &'static Messages,
+}
+
+impl MessageCatalog {
+    /// Unsupported locales fall back to English
+    pub fn new(locale: &str) -> Self {
+        let catalogs = catalogs();
+        Self {
+            messages: catalogs
+                .get(locale)
+                .unwrap_or_else(|| &catalogs[DEFAULT_LOCALE]),
+        }
+    }
+
+    fn get(&self, key: &str, args: &[(&str, &dyn ToString)]) -> String {
+        let template = self
+            .messages
+            .get(key)
+            .or_else(|| catalogs()[DEFAULT_LOCALE].get(key))
+            .map(String::as_str)
+            .unwrap_or(key);
+        // One pass, so placeholders inside the values themselves are left alone
+        let mut message = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            message.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let value = rest.find('}').and_then(|end| {
+                let (_, value) = args.iter().find(|(name, _)| *name == &rest[1..end])?;
+                Some((value.to_string(), end))
+            });
+            if let Some((value, end)) = value {
+                message.push_str(&value);
+                rest = &rest[end + 1..];
+            } else {
+                message.push('{');
+                rest = &rest[1..];
+            }
+        }
+        message.push_str(rest);
+        message
+    }
+
+    pub fn help_hint(&self) -> String {
+        self.get("help_hint", &[])
+    }
+    pub fn command_not_recognized(&self, command: &str) -> String {
+        format!(
+            "{}\n{}",
+            self.get("command_not_recognized", &[("command", &command)]),
+            self.help_hint()
+        )
+    }
+    pub fn info_not_recognized(&self, command: &str) -> String {
+        self.get("info_not_recognized", &[("command", &command)])
+    }
+    pub fn available_commands(&self, bot: &str) -> String {
+        self.get("available_commands", &[("bot", &bot)])
+    }
+    pub fn to_participate(&self) -> String {
+        self.get("to_participate", &[])
+    }
+    pub fn greeting(&self, name: &str) -> String {
+        self.get("greeting", &[("name", &name)])
+    }
+    pub fn name_updated(&self, name: &str) -> String {
+        self.get("name_updated", &[("name", &name)])
+    }
+    pub fn name_too_long(&self, length: usize, max: usize) -> String {
+        self.get("name_too_long", &[("length", &length), ("max", &max)])
+    }
+    pub fn unsubscribed(&self) -> String {
+        self.get("unsubscribed", &[])
+    }
+    pub fn language_updated(&self) -> String {
+        self.get("language_updated", &[])
+    }
+    pub fn timezone_updated(&self, timezone: &str) -> String {
+        self.get("timezone_updated", &[("timezone", &timezone)])
+    }
+    pub fn timezone_not_recognized(&self, timezone: &str, suggestions: &[&str]) -> String {
+        self.get(
+            "timezone_not_recognized",
+            &[
+                ("timezone", &timezone),
+                ("suggestions", &suggestions.join(", ")),
+            ],
+        )
+    }
+    pub fn language_not_supported(&self, language: &str) -> String {
+        self.get(
+            "language_not_supported",
+            &[("language", &language), ("supported", &supported_locales())],
+        )
+    }
+    pub fn too_many_registrations(&self) -> String {
+        self.get("too_many_registrations", &[])
+    }
+    pub fn recent_commands(&self, commands: &[String]) -> String {
+        if commands.is_empty() {
+            return self.get("no_recent_commands", &[]);
+        }
+        self.get(
+            "recent_commands",
+            &[
+                ("count", &commands.len()),
+                ("commands", &commands.join(", ")),
+            ],
+        )
+    }
+    pub fn rate_limited(&self) -> String {
+        self.get("rate_limited", &[])
+    }
+    pub fn profile(&self, name: &str, number: &str, registered: &str, last_active: &str) -> String {
+        self.get(
+            "profile",
+            &[
+                ("name", &name),
+                ("number", &number),
+                ("registered", &registered),
+                ("last_active", &last_active),
+            ],
+        )
+    }
+    pub fn my_data(&self, data: &UserData) -> String {
+        self.get(
+            "my_data",
+            &[
+                ("number", &data.number),
+                ("name", &data.name),
+                ("locale", &data.locale),
+                ("timezone", &data.timezone),
+                ("registered", &data.registered),
+                ("last_active", &data.last_active),
+                ("commands", &self.list(data.commands)),
+                ("reminders", &self.list(data.reminders)),
+                ("invites", &self.list(data.invites)),
+            ],
+        )
+    }
+    fn list(&self, items: &[String]) -> String {
+        if items.is_empty() {
+            return self.get("none", &[]);
+        }
+        items
+            .iter()
+            .map(|item| format!("- {item}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    pub fn invite_line(&self, number: &str, sent_at: &str) -> String {
+        self.get("invite_line", &[("number", &number), ("sent_at", &sent_at)])
+    }
+    // commands are (command, successes, errors, P95 latency over the last minute)
+    pub fn stats(
+        &self,
+        users: i32,
+        failed: i32,
+        commands: &[(String, i64, i64, Option<Duration>)],
+    ) -> String {
+        let commands = if commands.is_empty() {
+            self.get("none", &[])
+        } else {
+            commands
+                .iter()
+                .map(|(command, ok, error, p95)| {
+                    let p95 = p95.map_or("-".to_string(), |p95| format!("{}ms", p95.as_millis()));
+                    self.get(
+                        "command_stats",
+                        &[
+                            ("command", command),
+                            ("ok", ok),
+                            ("error", error),
+                            ("p95", &p95),
+                        ],
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        self.get(
+            "stats",
+            &[
+                ("users", &users),
+                ("failed", &failed),
+                ("commands", &commands),
+            ],
+        )
+    }
+    pub fn invalid_number(&self, number: &str) -> String {
+        self.get("invalid_number", &[("number", &number)])
+    }
+    pub fn already_invited(&self, number: &str) -> String {
+        self.get("already_invited", &[("number", &number)])
+    }
+    pub fn invite_sent(&self, number: &str) -> String {
+        self.get("invite_sent", &[("number", &number)])
+    }
+    pub fn landline_not_supported(&self) -> String {
+        self.get("landline_not_supported", &[])
+    }
+    pub fn reminder_set(&self, due_at: &str, recurrence: Option<Recurrence>) -> String {
+        match recurrence {
+            Some(recurrence) => self.get(
+                "recurring_reminder_set",
+                &[
+                    ("due_at", &due_at),
+                    ("recurrence", &self.recurrence(Some(recurrence))),
+                ],
+            ),
+            None => self.get("reminder_set", &[("due_at", &due_at)]),
+        }
+    }
+    fn recurrence(&self, recurrence: Option<Recurrence>) -> String {
+        match recurrence {
+            None => self.get("once", &[]),
+            Some(Recurrence::Daily) => self.get("daily", &[]),
+            Some(Recurrence::Weekly) => self.get("weekly", &[]),
+            Some(Recurrence::Monthly) => self.get("monthly", &[]),
+        }
+    }
+    pub fn reminder_line(
+        &self,
+        id: i64,
+        due_at: &str,
+        recurrence: Option<Recurrence>,
+        message: &str,
+    ) -> String {
+        self.get(
+            "reminder_line",
+            &[
+                ("id", &id),
+                ("due_at", &due_at),
+                ("recurrence", &self.recurrence(recurrence)),
+                ("message", &message),
+            ],
+        )
+    }
+    pub fn reminders(&self, reminders: &[String]) -> String {
+        if reminders.is_empty() {
+            return self.get("no_reminders", &[]);
+        }
+        self.get("reminders", &[("reminders", &reminders.join("\n"))])
+    }
+    pub fn reminder_cancelled(&self, id: i64) -> String {
+        self.get("reminder_cancelled", &[("id", &id)])
+    }
+    pub fn reminder_not_found(&self, id: i64) -> String {
+        self.get("reminder_not_found", &[("id", &id)])
+    }
+    pub fn reminder(&self, message: &str) -> String {
+        self.get("reminder", &[("message", &message)])
+    }
+    pub fn welcome(&self) -> String {
+        self.get("welcome", &[])
+    }
+    pub fn command_description(&self, command: Command) -> String {
+        self.get(&format!("description_{command}"), &[])
+    }
+    pub fn usage(&self, command: Command) -> String {
+        self.get("usage", &[("command", &command)])
+    }
+    pub fn usage_with_parameter(&self, command: Command) -> String {
+        self.get(
+            "usage_with_parameter",
+            &[
+                ("command", &command),
+                ("parameter", &self.get(&format!("parameter_{command}"), &[])),
+            ],
+        )
+    }
+    pub fn example(&self, command: Command, example: &str) -> String {
+        self.get("example", &[("command", &command), ("example", &example)])
+    }
+    pub fn hint(&self, usage: &str, description: &str, example: &str) -> String {
+        self.get(
+            "hint",
+            &[
+                ("usage", &usage),
+                ("description", &description),
+                ("example", &example),
+            ],
+        )
+    }
+    pub fn command_info(&self, usage: &str, description: &str, example: &str) -> String {
+        self.get(
+            "command_info",
+            &[
+                ("usage", &usage),
+                ("description", &description),
+                ("example", &example),
+            ],
+        )
+    }
+    pub fn internal_error(&self) -> String {
+        self.get("internal_error", &[])
+    }
+}
+
+#[test]
+fn locales() {
+    let english = &catalogs()[DEFAULT_LOCALE];
+    for (locale, messages) in catalogs() {
+        for key in english.keys() {
+            assert!(messages.contains_key(key), "{locale} is missing {key}");
+        }
+        for key in messages.keys() {
+            assert!(english.contains_key(key), "{locale} has unknown key {key}");
+        }
+    }
+    assert_eq!(
+        MessageCatalog::new("es").greeting("Sam"),
+        "¡Hola, Sam!".to_string()
+    );
+    assert_eq!(
+        MessageCatalog::new("xx").greeting("Sam"),
+        "Hello, Sam!".to_string()
+    );
+}
+
+#[test]
+fn placeholders() {
+    let msg = MessageCatalog::new(DEFAULT_LOCALE);
+    // Values that look like placeholders are shown as they are
+    assert_eq!(
+        msg.invite_line("{sent_at}", "2026-10-14"),
+        "{sent_at} on 2026-10-14"
+    );
+    assert_eq!(msg.greeting("{name} {x"), "Hello, {name} {x!");
+}
+
+#[test]
+fn command_docs_translated() {
+    for (locale, messages) in catalogs() {
+        for command in enum_iterator::all::<Command>() {
+            let key = format!("description_{command}");
+            assert!(messages.contains_key(&key), "{locale} is missing {key}");
+        }
+    }
+    assert_eq!(
+        Command::lang.usage(&MessageCatalog::new("es")),
+        "Responde \"lang X\", donde X es un código de idioma"
+    );
+}
@@ -0,0 +1,74 @@
+use std::{env, time::Duration};
+
+use openapi::apis::configuration::Configuration;
+use serde::Deserialize;
+use sqlx::{query, Pool, Sqlite};
+
+use crate::error::BotError;
+
+const LOOKUP_URL: &str = "https://lookups.twilio.com/v1/PhoneNumbers";
+
+#[derive(Deserialize)]
+struct LookupResponse {
+    carrier: Option<Carrier>,
+}
+
+#[derive(Deserialize)]
+struct Carrier {
+    #[serde(rename = "type")]
+    number_type: Option<String>,
+}
+
+// Lookups cost money, so they are off unless TWILIO_ENABLE_LOOKUP=true
+pub(crate) fn lookup_enabled() -> bool {
+    env::var("TWILIO_ENABLE_LOOKUP").is_ok_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+/// The carrier's type for a number, e.g. "mobile", asking Twilio only if it isn't cached
+pub(crate) async fn number_type(
+    pool: &Pool<Sqlite>,
+    twilio_config: &Configuration,
+    number: &str,
+) -> Result<Option<String>, BotError> {
+    if let Some(cached) = query!(
+        r#"select type as "number_type?: String" from number_types where number = ?"#,
+        number
+    )
+    .fetch_optional(pool)
+    .await?
+    {
+        return Ok(cached.number_type);
+    }
+    let mut request = twilio_config
+        .client
+        .get(format!("{LOOKUP_URL}/{number}"))
+        .query(&[("Type", "carrier")])
+        .timeout(Duration::from_secs(5));
+    if let Some((username, password)) = &twilio_config.basic_auth {
+        request = request.basic_auth(username, password.as_ref());
+    }
+    let response: LookupResponse = request.send().await?.error_for_status()?.json().await?;
+    let number_type = response.carrier.and_then(|carrier| carrier.number_type);
+    query!(
+        "insert into number_types (number, type) values (?, ?)
+        on conflict (number) do update set type = excluded.type, looked_up_at = unixepoch()",
+        number,
+        number_type
+    )
+    .execute(pool)
+    .await?;
+    Ok(number_type)
+}
+
+#[sqlx::test]
+async fn cached_lookup(pool: Pool<Sqlite>) -> anyhow::Result<()> {
+    query!("insert into number_types (number, type) values ('+15551234567', 'landline')")
+        .execute(&pool)
+        .await?;
+    // The default configuration can't reach Twilio, so this must come from the cache
+    assert_eq!(
+        number_type(&pool, &Configuration::default(), "+15551234567").await?,
+        Some("landline".to_string())
+    );
+    Ok(())
+}
@@ -1,45 +1,201 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use axum::{
+    http::{header, HeaderValue},
+    middleware,
     response::{Html, IntoResponse},
-    routing::post,
+    routing::{get, post},
     Extension, Form, Router,
 };
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use dotenv::dotenv;
 use enum_iterator::all;
-use log::*;
 use openapi::apis::{
     api20100401_message_api::{create_message, CreateMessageParams},
     configuration::Configuration,
 };
+use sqlx::migrate::Migrate;
 use sqlx::{query, query_as, Pool, Sqlite};
-use std::env;
+use std::{
+    collections::HashSet,
+    env,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::oneshot;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tracing::{debug, error, info, trace, warn};
+use tracing_subscriber::EnvFilter;
 
-use crate::command::Command;
+use crate::{
+    bot::{bot_for, load_bots, BotConfig},
+    chunks::{split_message, split_segments, MAX_CHUNK_LEN},
+    circuit::{SharedTwilioCircuit, TwilioCircuit},
+    client_ip::{extract_client_ip, ClientIp},
+    command::Command,
+    error::{env_number, env_var, BotError, SendError},
+    health::{handle_health, handle_ready},
+    i18n::{is_supported, MessageCatalog, UserData, DEFAULT_LOCALE},
+    lookup::{lookup_enabled, number_type},
+    metrics::{handle_metrics, record_error, record_message, record_outbound, CommandMetrics},
+    queue::{max_outbound_per_min, send_workers, MessageQueue, QueuedMessage},
+    rate_limit::RateLimiter,
+    reminders::{deliver_due_reminders, parse_reminder, ParsedReminder},
+    request_id::{assign_request_id, RequestId},
+    signature::validate_signature,
+    state::{AppState, SharedState},
+    status_callback::handle_status_callback,
+    timezone::{format_time, parse_timezone, user_timezone},
+    twiml::ResponseBuilder,
+};
 
+mod bot;
+mod chunks;
+mod circuit;
+mod client_ip;
 mod command;
+mod db;
+mod error;
+mod health;
+mod i18n;
+mod lookup;
+mod metrics;
+mod processed_messages;
+mod queue;
+mod rate_limit;
+mod reminders;
+mod request_id;
+mod signature;
+mod state;
+mod status_callback;
+mod timezone;
+mod twiml;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv()?;
-    env_logger::init();
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+    if env::args().any(|arg| arg == "--migrate-only") {
+        let pool = db::connect(&env::var("DATABASE_URL")?).await?;
+        return migrate(&pool).await;
+    }
     info!("Starting up");
-    let twilio_config = Configuration {
+    let twilio_config = Arc::new(Configuration {
         basic_auth: Some((
             env::var("TWILIO_API_KEY_SID")?,
             Some(env::var("TWILIO_API_KEY_SECRET")?),
         )),
         ..Default::default()
-    };
-    send(
+    });
+    let circuit = SharedTwilioCircuit::default();
+    send_long(
         &twilio_config,
+        &circuit,
         env::var("CLIENT_NUMBER")?,
-        "Server is starting up".to_string(),
+        "Server is starting up",
     )
     .await?;
-    let pool = sqlx::SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
+    let metrics_handle = metrics::install()?;
+    let pool = db::connect(&env::var("DATABASE_URL")?).await?;
+    let bots = load_bots()?;
+    info!("Loaded {} bot configurations", bots.len());
+    let auth_token = env::var("TWILIO_AUTH_TOKEN")?;
+    let admins = admin_numbers();
+    info!("Loaded {} admin numbers", admins.len());
+    tokio::spawn({
+        let pool = pool.clone();
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+                if let Err(error) = processed_messages::cleanup(&pool).await {
+                    error!("Failed to clean up processed messages: {error:?}");
+                }
+            }
+        }
+    });
+    let max_inactive_days = max_inactive_days();
+    if max_inactive_days > 0 {
+        tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                loop {
+                    match cleanup_inactive_users(&pool, max_inactive_days).await {
+                        Ok(pruned) => info!("Pruned {pruned} inactive users"),
+                        Err(error) => error!("Failed to prune inactive users: {error:?}"),
+                    }
+                    tokio::time::sleep(Duration::from_secs(24 * 60 * 60)).await;
+                }
+            }
+        });
+    }
+    let queue = MessageQueue::start(send_workers(), max_outbound_per_min(), {
+        let twilio_config = twilio_config.clone();
+        let circuit = circuit.clone();
+        move |to, body| {
+            let twilio_config = twilio_config.clone();
+            let circuit = circuit.clone();
+            async move { send_long(&twilio_config, &circuit, to, &body).await }
+        }
+    });
+    let state = Arc::new(AppState {
+        pool: pool.clone(),
+        twilio_config: twilio_config.clone(),
+        queue,
+        bots,
+        admins,
+        limiter: RateLimiter::from_env(),
+        max_registrations_per_ip: max_registrations_per_ip(),
+        command_metrics: Mutex::default(),
+        metrics_handle,
+    });
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                state.limiter.cleanup();
+            }
+        }
+    });
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            loop {
+                match deliver_due_reminders(&state.pool, &state.queue).await {
+                    Ok(0) => {}
+                    Ok(delivered) => info!("Delivering {delivered} reminders"),
+                    Err(error) => error!("Failed to deliver reminders: {error:?}"),
+                }
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+        }
+    });
     let app = Router::new()
         .route("/", post(handle_incoming_sms))
-        .layer(Extension(pool));
+        .route("/status", post(handle_status_callback))
+        .route_layer(middleware::from_fn_with_state(
+            auth_token,
+            validate_signature,
+        ))
+        .route("/health", get(handle_health))
+        .route("/health/ready", get(handle_ready))
+        .route("/metrics", get(handle_metrics))
+        .layer(Extension(state))
+        .layer(middleware::from_fn(extract_client_ip))
+        .layer(middleware::from_fn(assign_request_id))
+        // Responses depend on the POST body, so they must never be cached
+        .layer(SetResponseHeaderLayer::overriding(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-store"),
+        ))
+        .layer(SetResponseHeaderLayer::overriding(
+            header::VARY,
+            HeaderValue::from_static("*"),
+        ));
     let listener = tokio::net::TcpListener::bind(format!(
         "{}:{}",
         env::var("CALLBACK_IP")?,
@@ -47,8 +203,79 @@ async fn main() -> Result<()> {
     ))
     .await?;
     info!("Listening on {}", listener.local_addr()?);
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    info!("Shutting down");
+    pool.close().await;
+    send_long(
+        &twilio_config,
+        &circuit,
+        env::var("CLIENT_NUMBER")?,
+        "Server is shutting down",
+    )
+    .await?;
+    Ok(())
+}
+
+// Resolves on Ctrl+C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {error:?}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(error) => {
+                error!("Failed to listen for SIGTERM: {error:?}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
 
+// Applies any pending migrations, printing each one that was applied
+async fn migrate(pool: &Pool<Sqlite>) -> Result<()> {
+    let migrator = sqlx::migrate!();
+    let mut connection = pool.acquire().await?;
+    connection.ensure_migrations_table().await?;
+    let applied = connection
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect::<HashSet<_>>();
+    drop(connection);
+    let pending = migrator
+        .iter()
+        .filter(|migration| {
+            !migration.migration_type.is_down_migration() && !applied.contains(&migration.version)
+        })
+        .collect::<Vec<_>>();
+    if pending.is_empty() {
+        println!("No migrations to apply");
+        return Ok(());
+    }
+    migrator
+        .run(pool)
+        .await
+        .context("While running migrations")?;
+    for migration in pending {
+        println!("Applied {} {}", migration.version, migration.description);
+    }
     Ok(())
 }
 
@@ -58,170 +285,939 @@ async fn main() -> Result<()> {
 struct SmsMessage {
     Body: String,
     From: String,
+    To: String,
+    MessageSid: Option<String>,
 }
 
 struct User {
     number: String,
-    #[allow(dead_code)]
     name: String,
+    locale: String,
+    created_at: i64,
+    last_active: i64,
+    timezone: String,
 }
 
 // Handler for incoming SMS messages
+#[tracing::instrument(skip_all, fields(request_id = %request_id, from = %loggable_number(&message.From)))]
 async fn handle_incoming_sms(
-    Extension(pool): Extension<Pool<Sqlite>>,
+    Extension(state): Extension<SharedState>,
+    Extension(ClientIp(client_ip)): Extension<ClientIp>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
     Form(message): Form<SmsMessage>,
 ) -> impl IntoResponse {
-    let response = match process_message(&pool, message).await {
-        Ok(response) => response,
-        Err(error) => {
-            error!("Error: {error:?}");
-            "Internal Server Error!".to_string()
+    debug!("Started request {request_id}");
+    let sid = message.MessageSid.clone();
+    if let Some(sid) = &sid {
+        match processed_messages::claim(&state.pool, sid, &message.From).await {
+            Ok(None) => {}
+            Ok(Some(previous)) => {
+                info!("Already processed {sid}, replaying the original response");
+                return Html(previous.unwrap_or_else(|| ResponseBuilder::new().build()));
+            }
+            // Better to risk handling it twice than not at all
+            Err(error) => error!("Error checking for duplicate message {sid}: {error:?}"),
         }
-    };
-    debug!("Sending response: {response}");
-    Html(format!(
-        r#"
-        <?xml version="1.0" encoding="UTF-8"?>
-        <Response>
-        <Message>{response}</Message>
-        </Response>
-        "#
-    ))
+    }
+    let bot = bot_for(&state.bots, &message.To);
+    let result = process_message(&state, &bot, client_ip, message).await;
+    let succeeded = result.is_ok();
+    let response = result.unwrap_or_else(|error| {
+        error!("Error: {error:?}");
+        record_error();
+        MessageCatalog::new(DEFAULT_LOCALE).internal_error()
+    });
+    // Long responses go out as several messages
+    let chunks = split_message(&response, MAX_CHUNK_LEN);
+    // Replies can hold anything about the user, up to their whole mydata export
+    if pii_logging_enabled() {
+        debug!("Sending response: {response}");
+    } else {
+        debug!(
+            "Sending response: len={}, parts={}",
+            response.len(),
+            chunks.len()
+        );
+    }
+    let twiml = chunks
+        .iter()
+        // Nothing at all is sent back to opted-out numbers
+        .filter(|chunk| !chunk.is_empty())
+        .fold(ResponseBuilder::new(), |builder, chunk| {
+            builder.message(chunk)
+        })
+        .build();
+    if let Some(sid) = &sid {
+        // Only successes are replayed; a retry after an error gets another try
+        let saved = if succeeded {
+            processed_messages::save_response(&state.pool, sid, &twiml).await
+        } else {
+            processed_messages::release(&state.pool, sid).await
+        };
+        if let Err(error) = saved {
+            error!("Error recording the outcome of {sid}: {error:?}");
+        }
+    }
+    debug!("Finished request {request_id}");
+    Html(twiml)
 }
 
-async fn process_message(pool: &Pool<Sqlite>, message: SmsMessage) -> anyhow::Result<String> {
+#[tracing::instrument(skip_all, fields(from = %loggable_number(&message.From)))]
+async fn process_message(
+    state: &AppState,
+    bot: &BotConfig,
+    client_ip: Option<IpAddr>,
+    message: SmsMessage,
+) -> Result<String, BotError> {
+    let AppState {
+        pool,
+        queue,
+        admins,
+        command_metrics,
+        ..
+    } = state;
     let SmsMessage {
         Body: body,
         From: from,
+        ..
     } = message;
-    debug!("Received from {from}: {body}");
+    // Before rate limiting, so a number flooding us can still opt out
+    if let Some(response) = handle_opt_out(pool, bot, &from, &body).await? {
+        return Ok(response);
+    }
+    if !state.limiter.check(&from) {
+        warn!("Rate limiting {}", loggable_number(&from));
+        return Ok(MessageCatalog::new(DEFAULT_LOCALE).rate_limited());
+    }
+    if pii_logging_enabled() {
+        trace!("Received SMS from={from}, body={body:?}");
+    } else {
+        trace!(
+            "Received SMS from={}, len={}",
+            mask_number(&from),
+            body.len()
+        );
+    }
 
     let mut words = body.trim().split_ascii_whitespace();
     let command_word = words.next();
-    let command = command_word.map(|word| Command::try_from(word));
+    let command = command_word.map(Command::try_from);
 
-    let Some(User {
-        number, name: _, ..
-    }) = query_as!(User, "select * from users where number = ?", from)
+    record_message(&match &command {
+        Some(Ok(command)) => command.to_string(),
+        Some(Err(_)) => "unknown".to_string(),
+        None => "none".to_string(),
+    });
+
+    let Some(user) = query_as!(User, "select * from users where number = ?", from)
         .fetch_optional(pool)
         .await?
     else {
-        return onboard_new_user(command, words, &from, pool).await;
+        return onboard_new_user(command, words, &from, client_ip, state, bot).await;
     };
+    query!(
+        "update users set last_active = unixepoch() where number = ?",
+        from
+    )
+    .execute(pool)
+    .await?;
+    let msg = MessageCatalog::new(&user.locale);
 
     let Some(command) = command else {
-        return Ok(Command::h.hint());
+        return Ok(msg.help_hint());
     };
 
-    let Ok(command) = command else {
-        return Ok(format!(
-            "We didn't recognize that command word: \"{}\".\n{}",
-            command_word.unwrap(),
-            Command::h.hint()
-        ));
+    // Admin-only commands are indistinguishable from unknown ones to everyone else
+    let Some(command) = command
+        .ok()
+        .filter(|command| is_available(command, &from, admins))
+    else {
+        return Ok(msg.command_not_recognized(command_word.unwrap()));
     };
+    let args = words.clone().collect::<Vec<_>>().join(" ");
 
-    let response = match command {
-        // I would use HELP for the help command, but Twilio intercepts and does not relay that
-        Command::h => {
-            let available_commands = format!(
-                "Available commands:\n{}\n",
-                all::<Command>()
-                    .map(|c| format!("- {c}"))
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            );
-            format!("{available_commands}\n{}", Command::info.hint())
-        }
-        Command::name => match process_name(words) {
-            Ok(name) => {
-                query!("update users set name = ? where number = ?", name, from)
-                    .execute(pool)
-                    .await?;
-                format!("Your name has been updated to \"{name}\"")
+    // Errors are caught here so they can be counted before being passed on
+    let start = Instant::now();
+    let result = async {
+        Ok::<_, BotError>(match command {
+            // I would use HELP for the help command, but Twilio intercepts and does not relay that
+            Command::h => handle_help(bot, &from, admins, &msg),
+            Command::name => match process_name(words, &msg) {
+                Ok(name) => {
+                    query!("update users set name = ? where number = ?", name, from)
+                        .execute(pool)
+                        .await?;
+                    msg.name_updated(&name)
+                }
+                Err(BotError::Validation(hint)) => hint,
+                Err(error) => return Err(error),
+            },
+            // The bare keywords are handled by handle_opt_out, but "stop now" still means stop
+            Command::stop => opt_out(pool, &from).await?,
+            Command::info => {
+                let command_text = words.next();
+                if let Some(command) = command_text.map(Command::try_from) {
+                    if let Some(command) = command
+                        .ok()
+                        .filter(|command| is_available(command, &from, admins))
+                    {
+                        command.info(&msg)
+                    } else {
+                        msg.info_not_recognized(command_text.unwrap())
+                    }
+                } else {
+                    Command::info.hint(&msg)
+                }
+            }
+            Command::lang => match words.next().map(str::to_lowercase) {
+                Some(locale) if is_supported(&locale) => {
+                    query!("update users set locale = ? where number = ?", locale, from)
+                        .execute(pool)
+                        .await?;
+                    MessageCatalog::new(&locale).language_updated()
+                }
+                Some(locale) => msg.language_not_supported(&locale),
+                None => Command::lang.usage(&msg),
+            },
+            // Zone names use underscores for spaces, e.g. America/New_York
+            Command::tz => match words.collect::<Vec<_>>().join("_") {
+                name if name.is_empty() => Command::tz.usage(&msg),
+                name => match parse_timezone(&name) {
+                    Ok(timezone) => {
+                        let timezone = timezone.name();
+                        query!(
+                            "update users set timezone = ? where number = ?",
+                            timezone,
+                            from
+                        )
+                        .execute(pool)
+                        .await?;
+                        msg.timezone_updated(timezone)
+                    }
+                    Err(suggestions) => msg.timezone_not_recognized(&name, &suggestions),
+                },
+            },
+            Command::status => handle_status(pool, &from, &msg).await?,
+            Command::profile => handle_profile(&user, &msg),
+            Command::mydata => handle_mydata(pool, &user, &msg).await?,
+            // Numbers are often typed with spaces, e.g. (555) 123-4567
+            Command::invite => match words.collect::<Vec<_>>().join(" ") {
+                number if number.is_empty() => Command::invite.usage(&msg),
+                number => handle_invite(pool, queue, bot, &from, &number, &msg).await?,
+            },
+            Command::remind => {
+                let words = words.collect::<Vec<_>>();
+                handle_remind(pool, &from, &words, user_timezone(&user.timezone), &msg).await?
             }
-            Err(hint) => hint.to_string(),
-        },
-        Command::stop => {
-            query!("delete from users where number = ?", number)
+            Command::reminders => {
+                handle_reminders(pool, &from, user_timezone(&user.timezone), &msg).await?
+            }
+            Command::unremind => match words.next().map(str::parse::<i64>) {
+                Some(Ok(id)) => handle_cancel_reminder(pool, &from, id, &msg).await?,
+                _ => Command::unremind.hint(&msg),
+            },
+            Command::stats => handle_stats(pool, command_metrics, &msg).await?,
+            Command::debug => format!(
+                "number: {}\nname: {}\nlocale: {}\ntimezone: {}\ncreated_at: {}\nlast_active: {}",
+                user.number,
+                user.name,
+                user.locale,
+                user.timezone,
+                user.created_at,
+                user.last_active
+            ),
+        })
+    }
+    .await;
+    command_metrics
+        .lock()
+        .unwrap()
+        .record(&command.to_string(), start.elapsed());
+    record_outcome(pool, command, result.is_ok()).await;
+    let response = result?;
+    if command == Command::stop {
+        // Nothing left to record history against
+        return Ok(response);
+    }
+    let command_text = command.to_string();
+    query!(
+        "insert into command_history (user_number, command, args) values (?, ?, ?)",
+        from,
+        command_text,
+        args
+    )
+    .execute(pool)
+    .await?;
+    Ok(response)
+}
+
+// Only logs failures, since the command itself has already happened
+async fn record_outcome(pool: &Pool<Sqlite>, command: Command, ok: bool) {
+    let command_text = command.to_string();
+    let outcome = if ok { "ok" } else { "error" };
+    if let Err(error) = query!(
+        "insert into command_stats (command, outcome, count) values (?, ?, 1)
+        on conflict (command, outcome) do update set count = count + 1",
+        command_text,
+        outcome
+    )
+    .execute(pool)
+    .await
+    {
+        error!("Failed to record {outcome} for {command_text}: {error:?}");
+    }
+}
+
+async fn handle_status(
+    pool: &Pool<Sqlite>,
+    from: &str,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
+    Ok(msg.recent_commands(&recent_commands(pool, from).await?))
+}
+
+// Removes everything stored about a user. Tables referencing users cascade, but
+// they are also cleared explicitly so nothing survives if foreign keys are off.
+async fn delete_user(pool: &Pool<Sqlite>, number: &str) -> Result<(), BotError> {
+    let mut tx = pool.begin().await?;
+    query!("delete from command_history where user_number = ?", number)
+        .execute(&mut *tx)
+        .await?;
+    query!("delete from invites where inviter_number = ?", number)
+        .execute(&mut *tx)
+        .await?;
+    query!("delete from reminders where user_number = ?", number)
+        .execute(&mut *tx)
+        .await?;
+    // Cached replies can hold their data too, e.g. a mydata export
+    query!(
+        "delete from processed_messages where from_number = ?",
+        number
+    )
+    .execute(&mut *tx)
+    .await?;
+    query!("delete from users where number = ?", number)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// The keywords Twilio itself acts on, so we stay in sync with what it will deliver
+// when it forwards them rather than handling them itself
+const OPT_OUT_KEYWORDS: &[&str] = &["STOP", "STOPALL", "UNSUBSCRIBE", "CANCEL", "END", "QUIT"];
+const OPT_IN_KEYWORDS: &[&str] = &["START", "YES", "UNSTOP"];
+
+// Deletes everything about them and ignores them until they opt back in
+async fn opt_out(pool: &Pool<Sqlite>, from: &str) -> Result<String, BotError> {
+    let locale = query!("select locale from users where number = ?", from)
+        .fetch_optional(pool)
+        .await?
+        .map(|user| user.locale);
+    delete_user(pool, from).await?;
+    query!(
+        "insert into blocked_numbers (number) values (?) on conflict do nothing",
+        from
+    )
+    .execute(pool)
+    .await?;
+    info!("{} opted out", loggable_number(from));
+    // They won't actually see this when using Twilio
+    Ok(MessageCatalog::new(locale.as_deref().unwrap_or(DEFAULT_LOCALE)).unsubscribed())
+}
+
+// Returns a response when the message shouldn't be processed any further
+async fn handle_opt_out(
+    pool: &Pool<Sqlite>,
+    bot: &BotConfig,
+    from: &str,
+    body: &str,
+) -> Result<Option<String>, BotError> {
+    let blocked = query!("select number from blocked_numbers where number = ?", from)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+    let keyword = body.trim().to_uppercase();
+    match keyword.as_str() {
+        keyword if OPT_OUT_KEYWORDS.contains(&keyword) => Ok(Some(opt_out(pool, from).await?)),
+        keyword if blocked && OPT_IN_KEYWORDS.contains(&keyword) => {
+            query!("delete from blocked_numbers where number = ?", from)
                 .execute(pool)
                 .await?;
-            // They won't actually see this when using Twilio
-            "You've been unsubscribed. Goodbye!".to_string()
+            info!("{} opted back in", loggable_number(from));
+            Ok(Some(welcome(bot, &MessageCatalog::new(DEFAULT_LOCALE))))
         }
-        Command::info => {
-            let command_text = words.next();
-            if let Some(command) = command_text.map(|word| Command::try_from(word)) {
-                if let Ok(command) = command {
-                    format!(
-                        "{} to {}.{}",
-                        command.usage(),
-                        command.description(),
-                        command.example()
-                    )
-                } else {
-                    format!("Command \"{}\" not recognized", command_text.unwrap())
-                }
-            } else {
-                Command::info.hint()
-            }
+        // Twilio wouldn't deliver a reply anyway
+        _ if blocked => {
+            debug!("Ignoring message from opted-out {}", loggable_number(from));
+            Ok(Some(String::new()))
         }
-    };
-    Ok(response)
+        _ => Ok(None),
+    }
+}
+
+// Users who haven't sent anything in this many days are deleted; 0 keeps everyone
+fn max_inactive_days() -> i64 {
+    env_number("MAX_INACTIVE_DAYS").unwrap_or(365)
+}
+
+// Returns the number of users removed. Each goes through delete_user, so there's
+// only one place that knows which tables hold user data.
+async fn cleanup_inactive_users(
+    pool: &Pool<Sqlite>,
+    max_inactive_days: i64,
+) -> Result<u64, BotError> {
+    let cutoff = max_inactive_days * 24 * 60 * 60;
+    let inactive = query!(
+        "select number from users where last_active < unixepoch() - ?",
+        cutoff
+    )
+    .fetch_all(pool)
+    .await?;
+    for user in &inactive {
+        delete_user(pool, &user.number).await?;
+    }
+    Ok(inactive.len() as u64)
+}
+
+// Oldest first
+async fn recent_commands(pool: &Pool<Sqlite>, number: &str) -> Result<Vec<String>, BotError> {
+    Ok(query!(
+        "select command, args from command_history where user_number = ? order by rowid",
+        number
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| format!("{} {}", row.command, row.args).trim().to_string())
+    .collect())
+}
+
+// Formats a unix timestamp as YYYY-MM-DD
+fn format_date(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+fn handle_profile(user: &User, msg: &MessageCatalog) -> String {
+    msg.profile(
+        &user.name,
+        &redact_number(&user.number),
+        &format_date(user.created_at),
+        &format_date(user.last_active),
+    )
+}
+
+async fn handle_mydata(
+    pool: &Pool<Sqlite>,
+    user: &User,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
+    let invites = query!(
+        "select invitee_number, sent_at from invites where inviter_number = ? order by sent_at",
+        user.number
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|invite| msg.invite_line(&invite.invitee_number, &format_date(invite.sent_at)))
+    .collect::<Vec<_>>();
+    let timezone = user_timezone(&user.timezone);
+    Ok(msg.my_data(&UserData {
+        number: &user.number,
+        name: &user.name,
+        locale: &user.locale,
+        timezone: timezone.name(),
+        registered: &format_date(user.created_at),
+        last_active: &format_date(user.last_active),
+        commands: &recent_commands(pool, &user.number).await?,
+        reminders: &reminder_lines(pool, &user.number, timezone, msg).await?,
+        invites: &invites,
+    }))
 }
 
+async fn handle_stats(
+    pool: &Pool<Sqlite>,
+    command_metrics: &Mutex<CommandMetrics>,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
+    let users = query!("select count(*) as count from users")
+        .fetch_one(pool)
+        .await?
+        .count;
+    let failed = query!("select count(*) as count from message_status where status = 'failed'")
+        .fetch_one(pool)
+        .await?
+        .count;
+    let commands = query!(
+        r#"select command,
+        sum(case when outcome = 'ok' then count else 0 end) as "ok!: i64",
+        sum(case when outcome = 'error' then count else 0 end) as "error!: i64"
+        from command_stats group by command order by sum(count) desc"#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let p95 = command_metrics.lock().unwrap().recent_p95(&row.command);
+        (row.command, row.ok, row.error, p95)
+    })
+    .collect::<Vec<_>>();
+    Ok(msg.stats(users, failed, &commands))
+}
+
+fn handle_help(bot: &BotConfig, from: &str, admins: &[String], msg: &MessageCatalog) -> String {
+    let available_commands = format!(
+        "{}\n{}\n",
+        msg.available_commands(&bot.name),
+        all::<Command>()
+            .filter(|command| is_available(command, from, admins))
+            .map(|c| format!("- {c}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    format!("{available_commands}\n{}", Command::info.hint(msg))
+}
+
+#[tracing::instrument(skip_all, fields(from = %loggable_number(from)))]
 async fn onboard_new_user(
     command: Option<Result<Command, serde_json::Error>>,
     words: impl Iterator<Item = &str>,
     from: &str,
-    pool: &Pool<Sqlite>,
-) -> anyhow::Result<String> {
+    client_ip: Option<IpAddr>,
+    state: &AppState,
+    bot: &BotConfig,
+) -> Result<String, BotError> {
+    let pool = &state.pool;
+    let msg = MessageCatalog::new(DEFAULT_LOCALE);
     let Some(Ok(Command::name)) = command else {
-        return Ok(format!(
-            "Welcome to Sam Carey's experimental social server!\nTo participate:\n{}",
-            Command::name.hint()
-        ));
+        return Ok(welcome(bot, &msg));
     };
-    Ok(match process_name(words) {
+    // Only keep track of IPs when throttling by them
+    let ip = client_ip
+        .filter(|_| state.max_registrations_per_ip.is_some())
+        .map(|ip| ip.to_string());
+    if let (Some(ip), Some(max)) = (&ip, state.max_registrations_per_ip) {
+        let recent_registrations = query!(
+            "select count(*) as count from registrations
+            where ip = ? and registered_at > unixepoch() - 24 * 60 * 60",
+            ip
+        )
+        .fetch_one(pool)
+        .await?
+        .count;
+        if recent_registrations >= max {
+            warn!("Throttling registration from {ip}");
+            return Ok(msg.too_many_registrations());
+        }
+    }
+    Ok(match process_name(words, &msg) {
         Ok(name) => {
-            query!("insert into users (number, name) values (?, ?)", from, name)
-                .execute(pool)
-                .await?;
-            format!("Hello, {name}! {}", Command::h.hint())
+            query!(
+                "insert into users (number, name, last_active) values (?, ?, unixepoch())",
+                from,
+                name
+            )
+            .execute(pool)
+            .await?;
+            // Landlines can't receive texts, so don't let them register
+            if lookup_enabled() {
+                match number_type(pool, &state.twilio_config, from).await {
+                    Ok(Some(number_type)) if number_type == "landline" => {
+                        delete_user(pool, from).await?;
+                        return Ok(msg.landline_not_supported());
+                    }
+                    Ok(_) => {}
+                    Err(error) => warn!(
+                        "Lookup failed for {}, allowing registration: {error}",
+                        loggable_number(from)
+                    ),
+                }
+            }
+            if let Some(ip) = &ip {
+                query!("insert into registrations (ip) values (?)", ip)
+                    .execute(pool)
+                    .await?;
+            }
+            query!(
+                "update invites set registered_at = unixepoch()
+                where invitee_number = ? and registered_at is null",
+                from
+            )
+            .execute(pool)
+            .await?;
+            format!("{} {}", msg.greeting(&name), msg.help_hint())
         }
-        Err(hint) => hint.to_string(),
+        Err(BotError::Validation(hint)) => hint,
+        Err(error) => return Err(error),
     })
 }
 
-fn process_name<'a>(words: impl Iterator<Item = &'a str>) -> Result<String> {
+// What people see before they've registered
+fn welcome(bot: &BotConfig, msg: &MessageCatalog) -> String {
+    format!(
+        "{}\n{}\n{}",
+        bot.welcome_message.clone().unwrap_or_else(|| msg.welcome()),
+        msg.to_participate(),
+        Command::name.hint(msg)
+    )
+}
+
+async fn handle_invite(
+    pool: &Pool<Sqlite>,
+    queue: &MessageQueue,
+    bot: &BotConfig,
+    from: &str,
+    number: &str,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
+    let Some(number) = normalize_number(number) else {
+        return Ok(msg.invalid_number(number));
+    };
+    let number = number.as_str();
+    let recently_invited = query!(
+        "select count(*) as count from invites
+        where invitee_number = ? and sent_at > unixepoch() - 24 * 60 * 60",
+        number
+    )
+    .fetch_one(pool)
+    .await?
+    .count
+        > 0;
+    if recently_invited {
+        return Ok(msg.already_invited(number));
+    }
+    let registered = query!("select number from users where number = ?", number)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+    let blocked = query!(
+        "select number from blocked_numbers where number = ?",
+        number
+    )
+    .fetch_optional(pool)
+    .await?
+    .is_some();
+    // Reply the same way either way, so invites can't be used to find out who's registered
+    if !registered && !blocked {
+        // Recorded before it's sent, so repeats are refused while it waits in the queue
+        let invite = query!(
+            "insert into invites (inviter_number, invitee_number) values (?, ?)",
+            from,
+            number
+        )
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+        let invitation = MessageCatalog::new(DEFAULT_LOCALE);
+        let (callback, delivered) = oneshot::channel();
+        queue.enqueue(QueuedMessage {
+            origin: from.to_string(),
+            to: number.to_string(),
+            body: welcome(bot, &invitation),
+            callback: Some(callback),
+        });
+        tokio::spawn(forget_failed_invite(pool.clone(), invite, delivered));
+    }
+    Ok(msg.invite_sent(number))
+}
+
+// An invite that didn't go out doesn't count, so it can be retried right away
+async fn forget_failed_invite(
+    pool: Pool<Sqlite>,
+    invite: i64,
+    delivered: oneshot::Receiver<Result<(), BotError>>,
+) {
+    // Kept if the queue went away without saying, in case it was sent after all.
+    // The queue already logged why if it failed.
+    let Ok(Err(_)) = delivered.await else {
+        return;
+    };
+    if let Err(error) = query!("delete from invites where rowid = ?", invite)
+        .execute(&pool)
+        .await
+    {
+        error!("Failed to forget failed invite: {error:?}");
+    }
+}
+
+async fn handle_remind(
+    pool: &Pool<Sqlite>,
+    from: &str,
+    words: &[&str],
+    timezone: Tz,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
+    let Some(ParsedReminder {
+        due_at,
+        recurrence,
+        message,
+    }) = parse_reminder(words, Utc::now().with_timezone(&timezone))
+    else {
+        return Ok(Command::remind.hint(msg));
+    };
+    let timestamp = due_at.timestamp();
+    let recurrence_text = recurrence.map(|recurrence| recurrence.to_string());
+    query!(
+        "insert into reminders (user_number, message, due_at, recurrence) values (?, ?, ?, ?)",
+        from,
+        message,
+        timestamp,
+        recurrence_text
+    )
+    .execute(pool)
+    .await?;
+    Ok(msg.reminder_set(&format_time(due_at, timezone), recurrence))
+}
+
+async fn handle_reminders(
+    pool: &Pool<Sqlite>,
+    from: &str,
+    timezone: Tz,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
+    Ok(msg.reminders(&reminder_lines(pool, from, timezone, msg).await?))
+}
+
+// Unsent reminders, soonest first
+async fn reminder_lines(
+    pool: &Pool<Sqlite>,
+    from: &str,
+    timezone: Tz,
+    msg: &MessageCatalog,
+) -> Result<Vec<String>, BotError> {
+    Ok(query!(
+        "select id as \"id!\", due_at, recurrence, message from reminders
+        where user_number = ? and sent = 0 order by due_at",
+        from
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|reminder| {
+        msg.reminder_line(
+            reminder.id,
+            &DateTime::from_timestamp(reminder.due_at, 0)
+                .map(|due_at| format_time(due_at, timezone))
+                .unwrap_or_default(),
+            reminder
+                .recurrence
+                .and_then(|recurrence| recurrence.parse().ok()),
+            &reminder.message,
+        )
+    })
+    .collect())
+}
+
+async fn handle_cancel_reminder(
+    pool: &Pool<Sqlite>,
+    from: &str,
+    id: i64,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
+    let cancelled = query!(
+        "delete from reminders where id = ? and user_number = ? and sent = 0",
+        id,
+        from
+    )
+    .execute(pool)
+    .await?
+    .rows_affected()
+        > 0;
+    Ok(if cancelled {
+        msg.reminder_cancelled(id)
+    } else {
+        msg.reminder_not_found(id)
+    })
+}
+
+// E.164: a plus sign followed by up to 15 digits, the first of which isn't 0
+fn is_valid_number(number: &str) -> bool {
+    number.strip_prefix('+').is_some_and(|digits| {
+        (8..=15).contains(&digits.len())
+            && !digits.starts_with('0')
+            && digits.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+// Turns the ways people tend to type numbers into E.164, assuming North America
+// when there's no country code
+fn normalize_number(input: &str) -> Option<String> {
+    let stripped = input
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect::<String>();
+    let number = if stripped.starts_with('+') {
+        stripped
+    } else if stripped.len() == 10 {
+        format!("+1{stripped}")
+    } else if stripped.len() == 11 && stripped.starts_with('1') {
+        format!("+{stripped}")
+    } else {
+        return None;
+    };
+    is_valid_number(&number).then_some(number)
+}
+
+// New users allowed per client IP per 24 hours. Off unless set, because webhooks arrive
+// from Twilio's addresses rather than the texter's, so one IP covers many people.
+fn max_registrations_per_ip() -> Option<i32> {
+    env_number("MAX_REGISTRATIONS_PER_IP").filter(|&max| max > 0)
+}
+
+fn process_name<'a>(
+    words: impl Iterator<Item = &'a str>,
+    msg: &MessageCatalog,
+) -> Result<String, BotError> {
     let name = words.collect::<Vec<_>>().join(" ");
     if name.is_empty() {
-        bail!("{}", Command::name.usage());
+        return Err(BotError::Validation(Command::name.usage(msg)));
     }
     const MAX_NAME_LEN: usize = 20;
     if name.len() > MAX_NAME_LEN {
-        bail!(
-            "That name is {} characters long.\n\
-            Please shorten it to {MAX_NAME_LEN} characters or less.",
-            name.len()
-        );
+        return Err(BotError::Validation(
+            msg.name_too_long(name.len(), MAX_NAME_LEN),
+        ));
     }
     Ok(name)
 }
 
-async fn send(twilio_config: &Configuration, to: String, message: String) -> Result<()> {
+// ADMIN_NUMBERS is a comma-separated list of numbers
+fn admin_numbers() -> Vec<String> {
+    env::var("ADMIN_NUMBERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|admin| !admin.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn is_admin(from: &str, admins: &[String]) -> bool {
+    admins.iter().any(|admin| admin == from)
+}
+
+fn is_available(command: &Command, from: &str, admins: &[String]) -> bool {
+    !command.is_admin_only() || is_admin(from, admins)
+}
+
+fn pii_logging_enabled() -> bool {
+    env::var("ENABLE_PII_LOGGING").is_ok_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+// Hides all but the last 4 digits of a phone number
+fn mask_number(number: &str) -> String {
+    let hidden = number.chars().count().saturating_sub(4);
+    number
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i < hidden { '*' } else { c })
+        .collect()
+}
+
+// Shows a NANP number as "(555) 123-xxxx"; anything else is masked
+fn redact_number(number: &str) -> String {
+    match number.strip_prefix("+1") {
+        Some(digits) if digits.len() == 10 && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("({}) {}-xxxx", &digits[..3], &digits[3..6])
+        }
+        _ => mask_number(number),
+    }
+}
+
+fn loggable_number(number: &str) -> String {
+    if pii_logging_enabled() {
+        number.to_string()
+    } else {
+        mask_number(number)
+    }
+}
+
+// Sends a message of any length, split into numbered parts if necessary
+async fn send_long(
+    twilio_config: &Configuration,
+    circuit: &Mutex<TwilioCircuit>,
+    to: String,
+    message: &str,
+) -> Result<(), BotError> {
+    for chunk in split_message(message, MAX_CHUNK_LEN) {
+        send(twilio_config, circuit, to.clone(), chunk).await?;
+    }
+    Ok(())
+}
+
+// send_long's parts always fit in one segment, but this is what stands between
+// anything else that's too long and Twilio
+async fn send(
+    twilio_config: &Configuration,
+    circuit: &Mutex<TwilioCircuit>,
+    to: String,
+    message: String,
+) -> Result<(), BotError> {
+    for segment in split_segments(&message) {
+        send_segment(twilio_config, circuit, to.clone(), segment).await?;
+    }
+    Ok(())
+}
+
+const SEND_ATTEMPTS: u32 = 3;
+
+#[tracing::instrument(skip_all, fields(to = %loggable_number(&to)))]
+async fn send_segment(
+    twilio_config: &Configuration,
+    circuit: &Mutex<TwilioCircuit>,
+    to: String,
+    message: String,
+) -> Result<(), BotError> {
+    let to_logged = loggable_number(&to);
+    let len = message.len();
     let message_params = CreateMessageParams {
-        account_sid: env::var("TWILIO_ACCOUNT_SID")?,
+        account_sid: env_var("TWILIO_ACCOUNT_SID")?,
         to,
-        from: Some(env::var("SERVER_NUMBER")?),
+        from: Some(env_var("SERVER_NUMBER")?),
         body: Some(message),
+        // e.g. https://example.com/status
+        status_callback: env::var("STATUS_CALLBACK_URL")
+            .ok()
+            .filter(|url| !url.is_empty()),
         ..Default::default()
     };
-    let message = create_message(twilio_config, message_params)
-        .await
-        .context("While sending message")?;
-    trace!("Message sent with SID {}", message.sid.unwrap().unwrap());
-    Ok(())
+    let mut attempt = 1;
+    loop {
+        if !circuit.lock().unwrap().allow() {
+            return Err(SendError::Unavailable.into());
+        }
+        let start = Instant::now();
+        let result = create_message(twilio_config, message_params.clone())
+            .await
+            .map_err(SendError::from);
+        // Only Twilio's own trouble counts against it, not e.g. invalid numbers
+        match &result {
+            Err(SendError::Transient(_)) => circuit.lock().unwrap().record_failure(),
+            _ => circuit.lock().unwrap().record_success(),
+        }
+        match result {
+            Ok(message) => {
+                record_outbound(start.elapsed());
+                info!(
+                    "SMS sent to={to_logged}, len={len}, sid={}",
+                    message.sid.flatten().unwrap_or_default()
+                );
+                return Ok(());
+            }
+            Err(SendError::Transient(error)) if attempt < SEND_ATTEMPTS => {
+                // 1s, 2s, 4s, ...
+                let delay = Duration::from_secs(1 << (attempt - 1));
+                warn!(
+                    "Send attempt {attempt} to={to_logged} failed, retrying in {delay:?}: {error}"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -229,21 +1225,70 @@ mod test {
     use super::*;
     use futures::executor::block_on;
 
+    // Everything process_message needs, from AppState::test.
+    // Tests that need something else replace the field before sending.
+    struct TestApp {
+        state: AppState,
+        bot: BotConfig,
+        client_ip: Option<IpAddr>,
+    }
+
+    impl TestApp {
+        fn new(pool: Pool<Sqlite>) -> Self {
+            Self {
+                state: AppState::test(pool),
+                bot: BotConfig::default(),
+                client_ip: None,
+            }
+        }
+
+        // Only borrows self, so closures can pass it temporary strings
+        fn send(
+            &self,
+            from: &str,
+            body: &str,
+        ) -> impl std::future::Future<Output = Result<String, BotError>> + '_ {
+            let message = SmsMessage {
+                From: from.to_string(),
+                To: "SERVER_NUMBER".to_string(),
+                MessageSid: None,
+                Body: body.to_string(),
+            };
+            process_message(&self.state, &self.bot, self.client_ip, message)
+        }
+    }
+
     fn fixture(pool: Pool<Sqlite>) -> impl Fn(&str) {
+        let app = TestApp::new(pool);
         move |message: &str| {
             println!(">'{message}'");
-            let response = block_on(process_message(
-                &pool,
-                SmsMessage {
-                    From: "TEST_NUMBER".to_string(),
-                    Body: message.to_string(),
-                },
-            ))
-            .unwrap();
+            let response = block_on(app.send("TEST_NUMBER", message)).unwrap();
             println!("{response}\n\n");
         }
     }
 
+    #[test]
+    fn name_validation() {
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        assert_eq!(process_name("Sam C.".split(' '), &msg).unwrap(), "Sam C.");
+        assert!(matches!(
+            process_name(std::iter::empty(), &msg),
+            Err(BotError::Validation(_))
+        ));
+        assert!(matches!(
+            process_name(std::iter::once("Samuel Carey the Third"), &msg),
+            Err(BotError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn masking() {
+        assert_eq!(mask_number("+15551234567"), "********4567");
+        assert_eq!(mask_number("123"), "123");
+        assert_eq!(redact_number("+15551234567"), "(555) 123-xxxx");
+        assert_eq!(redact_number("+447911123456"), "*********3456");
+    }
+
     #[sqlx::test]
     async fn all(pool: Pool<Sqlite>) -> Result<()> {
         let fixture = fixture(pool);
@@ -257,10 +1302,573 @@ mod test {
         fixture("info x");
         fixture("info info");
         fixture("info name x");
+        fixture("debug");
+        fixture("lang es");
+        fixture("h");
+        fixture("lang xx");
+        fixture("status");
+        fixture("profile");
         fixture("yo");
         fixture("stop");
         fixture("yo");
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn translated_docs(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool);
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        let msg = MessageCatalog::new("es");
+        assert!(send("hi")
+            .await?
+            .starts_with(&MessageCatalog::new(DEFAULT_LOCALE).welcome()));
+        send("name Sam C.").await?;
+        send("lang es").await?;
+        let info = send("info lang").await?;
+        assert_eq!(info, Command::lang.info(&msg));
+        assert!(info.starts_with("Responde \"lang X\""));
+        assert!(send("h").await?.ends_with(&Command::info.hint(&msg)));
+        assert_eq!(send("tz").await?, Command::tz.usage(&msg));
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn command_history(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        send("name Sam C.").await?;
+        for body in ["h", "info name", "h", "info stop", "name Sam", "h"] {
+            send(body).await?;
+        }
+        assert_eq!(
+            send("status").await?,
+            "Your last 5 commands: info name, h, info stop, name Sam, h"
+        );
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn my_data(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |body: &str| app.send("+15551234567", body);
+        send("name Sam C.").await?;
+        let data = send("mydata").await?;
+        assert!(data.contains("Number: +15551234567"));
+        assert!(data.contains("Name: Sam C."));
+        assert!(data.contains("Language: en"));
+        assert!(data.contains(&format!(
+            "Last active: {}",
+            format_date(chrono::Utc::now().timestamp())
+        )));
+        assert!(data.contains("Time zone: UTC"));
+        assert!(data.contains("Recent commands:\n(none)"));
+        assert!(data.contains("Upcoming reminders:\n(none)"));
+        assert!(data.contains("Invites sent:\n(none)"));
+        assert!(data.contains("\"stop\""));
+        send("info name").await?;
+        send("tz Asia/Tokyo").await?;
+        send("remind at 3pm tomorrow call Alex").await?;
+        send("invite +15557654321").await?;
+        let data = send("mydata").await?;
+        assert!(data.contains("Time zone: Asia/Tokyo"));
+        assert!(data.contains("\n- invite +15557654321\nUpcoming reminders:"));
+        assert!(data.contains("Upcoming reminders:\n- #1 "));
+        assert!(data.contains(" JST (once): call Alex\n"));
+        let data = send("mydata").await?;
+        assert!(data.contains(&format!(
+            "Invites sent:\n- +15557654321 on {}\n",
+            format_date(chrono::Utc::now().timestamp())
+        )));
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn stop_purges_user_data(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        send("name Sam C.").await?;
+        send("h").await?;
+        processed_messages::claim(&pool, "SM123", "TEST_NUMBER").await?;
+        processed_messages::save_response(&pool, "SM123", "<Response/>").await?;
+        send("stop").await?;
+        let cached = query!("select count(*) as count from processed_messages")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(cached.count, 0);
+        let users = query!("select count(*) as count from users")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(users.count, 0);
+        let orphans = query!(
+            "select count(*) as count from command_history
+            where user_number not in (select number from users)"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(orphans.count, 0);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn opt_out(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        send("name Sam C.").await?;
+        assert_eq!(send("STOP").await?, msg.unsubscribed());
+        let users = query!("select count(*) as count from users")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(users.count, 0);
+        // Ignored entirely, even the commands that would sign them up again
+        assert_eq!(send("name Sam C.").await?, "");
+        assert_eq!(send("h").await?, "");
+        assert_eq!(send("start").await?, welcome(&app.bot, &msg));
+        assert!(send("name Sam C.").await?.starts_with("Hello"));
+        // Only the bare keyword counts
+        assert_ne!(send("unstop please").await?, "");
+
+        // Every keyword Twilio honors, in either direction
+        for (stop, start) in [
+            ("stopall", "yes"),
+            ("Unsubscribe", "unstop"),
+            ("end", "start"),
+        ] {
+            assert_eq!(send(stop).await?, msg.unsubscribed());
+            assert_eq!(send("h").await?, "");
+            assert_eq!(send(start).await?, welcome(&app.bot, &msg));
+        }
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn stop_command(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        send("name Sam C.").await?;
+        // Not a bare keyword, so this goes through Command::stop
+        assert_eq!(send("quit now").await?, msg.unsubscribed());
+        let users = query!("select count(*) as count from users")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(users.count, 0);
+        assert_eq!(send("name Sam C.").await?, "");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn opt_out_while_rate_limited(pool: Pool<Sqlite>) -> Result<()> {
+        let mut app = TestApp::new(pool.clone());
+        app.state.limiter = RateLimiter::new(1);
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        send("name Sam C.").await?;
+        assert_eq!(send("h").await?, msg.rate_limited());
+        assert_eq!(send("stop").await?, msg.unsubscribed());
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn stats(pool: Pool<Sqlite>) -> Result<()> {
+        let mut app = TestApp::new(pool.clone());
+        app.state.admins = vec!["ADMIN_NUMBER".to_string()];
+        let send = |from: &str, body: &str| app.send(from, body);
+        send("ADMIN_NUMBER", "name Admin").await?;
+        send("TEST_NUMBER", "name Sam C.").await?;
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        assert_eq!(
+            send("TEST_NUMBER", "stats").await?,
+            msg.command_not_recognized("stats")
+        );
+        assert_eq!(send("ADMIN_NUMBER", "stats").await?, msg.stats(2, 0, &[]));
+        send("TEST_NUMBER", "h").await?;
+        send("TEST_NUMBER", "h").await?;
+        // Latencies vary from run to run
+        let stats = send("ADMIN_NUMBER", "stats").await?;
+        assert!(stats.contains("\n- h: 2 ok, 0 failed, P95 "));
+        assert!(stats.contains("\n- stats: 1 ok, 0 failed, P95 "));
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn stats_failure(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        send("name Sam C.").await?;
+        query!("drop table command_stats").execute(&pool).await?;
+        // The name still changed, so saying otherwise would only prompt a retry
+        assert_eq!(
+            send("name Sam").await?,
+            MessageCatalog::new(DEFAULT_LOCALE).name_updated("Sam")
+        );
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn inactive_users(pool: Pool<Sqlite>) -> Result<()> {
+        query!(
+            "insert into users (number, name, last_active) values
+            ('ACTIVE', 'Active', unixepoch() - 86400),
+            ('INACTIVE', 'Inactive', unixepoch() - 3 * 86400)"
+        )
+        .execute(&pool)
+        .await?;
+        query!(
+            "insert into command_history (user_number, command, args) values ('INACTIVE', 'h', '')"
+        )
+        .execute(&pool)
+        .await?;
+        assert_eq!(cleanup_inactive_users(&pool, 2).await?, 1);
+        let users = query!("select number from users").fetch_all(&pool).await?;
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].number, "ACTIVE");
+        let history = query!("select count(*) as count from command_history")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(history.count, 0);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn invites(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |from: &str, body: &str| app.send(from, body);
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        send("+15551234567", "name Sam C.").await?;
+        send("+15557654321", "name Alex").await?;
+        assert_eq!(
+            send("+15551234567", "invite").await?,
+            Command::invite.usage(&msg)
+        );
+        assert_eq!(
+            send("+15551234567", "invite 555").await?,
+            msg.invalid_number("555")
+        );
+        // Already registered, so nothing is sent but the reply doesn't say so
+        assert_eq!(
+            send("+15551234567", "invite +15557654321").await?,
+            msg.invite_sent("+15557654321")
+        );
+        assert_eq!(
+            send("+15551234567", "invite (555) 765-4321").await?,
+            msg.invite_sent("+15557654321")
+        );
+        query!(
+            "insert into invites (inviter_number, invitee_number) values ('+15551234567', '+15550000000')"
+        )
+        .execute(&pool)
+        .await?;
+        assert_eq!(
+            send("+15551234567", "invite +15550000000").await?,
+            msg.already_invited("+15550000000")
+        );
+        send("+15550000000", "name Invitee").await?;
+        let invite =
+            query!("select registered_at from invites where invitee_number = '+15550000000'")
+                .fetch_one(&pool)
+                .await?;
+        assert!(invite.registered_at.is_some());
+        Ok(())
+    }
+
+    async fn invites_to(pool: &Pool<Sqlite>, number: &str) -> Result<i32> {
+        Ok(query!(
+            "select count(*) as count from invites where invitee_number = ?",
+            number
+        )
+        .fetch_one(pool)
+        .await?
+        .count)
+    }
+
+    #[sqlx::test]
+    async fn invite_delivery(pool: Pool<Sqlite>) -> Result<()> {
+        let (delivered, mut deliveries) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = TestApp::new(pool.clone());
+        app.state.queue = MessageQueue::start(1, u32::MAX, move |to: String, _| {
+            let failed = to == "+15550000001";
+            let _ = delivered.send(to);
+            async move {
+                if failed {
+                    Err(SendError::Unavailable.into())
+                } else {
+                    Ok(())
+                }
+            }
+        });
+        let send = |body: &str| app.send("+15551234567", body);
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        send("name Sam C.").await?;
+        assert_eq!(
+            send("invite +15550000001").await?,
+            msg.invite_sent("+15550000001")
+        );
+        assert_eq!(deliveries.recv().await.as_deref(), Some("+15550000001"));
+        // Forgotten once the queue reports the failure, so trying again isn't refused as a repeat
+        eventually(|| async { Ok(invites_to(&pool, "+15550000001").await? == 0) }).await?;
+        assert_eq!(
+            send("invite +15550000001").await?,
+            msg.invite_sent("+15550000001")
+        );
+        assert_eq!(deliveries.recv().await.as_deref(), Some("+15550000001"));
+
+        // Repeats are refused as soon as the first is queued, before it has been sent
+        send("invite +15550000002").await?;
+        assert_eq!(invites_to(&pool, "+15550000002").await?, 1);
+        assert_eq!(
+            send("invite +15550000002").await?,
+            msg.already_invited("+15550000002")
+        );
+        assert_eq!(deliveries.recv().await.as_deref(), Some("+15550000002"));
+        assert!(deliveries.try_recv().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn number_validation() {
+        assert!(is_valid_number("+15551234567"));
+        assert!(is_valid_number("+447911123456"));
+        assert!(!is_valid_number("15551234567"));
+        assert!(!is_valid_number("+0551234567"));
+        assert!(!is_valid_number("+1555"));
+        assert!(!is_valid_number("+1555123456789012"));
+        assert!(!is_valid_number("+1555-123-4567"));
+    }
+
+    #[test]
+    fn number_normalization() {
+        for input in [
+            "+15551234567",
+            "+1 555 123 4567",
+            "+1 (555) 123-4567",
+            "+1-555-123-4567",
+            "5551234567",
+            "555-123-4567",
+            "555.123.4567",
+            "555 123 4567",
+            "(555) 123-4567",
+            "(555)123-4567",
+            "15551234567",
+            "1-555-123-4567",
+            "1 (555) 123-4567",
+        ] {
+            assert_eq!(
+                normalize_number(input).as_deref(),
+                Some("+15551234567"),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            normalize_number("+44 7911 123456").as_deref(),
+            Some("+447911123456")
+        );
+        for input in [
+            "",
+            "555",
+            "123-4567",
+            "25551234567",
+            "555-123-456a",
+            "+0 555 123 4567",
+        ] {
+            assert_eq!(normalize_number(input), None, "{input}");
+        }
+    }
+
+    // For what the queue's callbacks update in the background: checks until it holds,
+    // giving up after a few seconds
+    async fn eventually<F, Fut>(mut check: F) -> Result<()>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<bool>>,
+    {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !check().await? {
+            anyhow::ensure!(Instant::now() < deadline, "Timed out waiting");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        Ok(())
+    }
+
+    // No reminder is partway through being sent
+    async fn reminders_settled(pool: &Pool<Sqlite>) -> Result<bool> {
+        Ok(
+            query!("select count(*) as count from reminders where claimed_at is not null")
+                .fetch_one(pool)
+                .await?
+                .count
+                == 0,
+        )
+    }
+
+    #[sqlx::test]
+    async fn reminders(pool: Pool<Sqlite>) -> Result<()> {
+        let (delivered, mut deliveries) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = TestApp::new(pool.clone());
+        app.state.queue = MessageQueue::start(1, u32::MAX, move |to, body| {
+            let _ = delivered.send((to, body));
+            async { Ok(()) }
+        });
+        let queue = &app.state.queue;
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        send("name Sam C.").await?;
+        assert_eq!(send("remind tomorrow").await?, Command::remind.hint(&msg));
+        assert!(send("remind at 3pm tomorrow call Alex")
+            .await?
+            .ends_with(" UTC."));
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 0);
+        query!("update reminders set due_at = unixepoch() - 1")
+            .execute(&pool)
+            .await?;
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 1);
+        assert_eq!(
+            deliveries.recv().await,
+            Some((
+                "TEST_NUMBER".to_string(),
+                MessageCatalog::new(DEFAULT_LOCALE).reminder("call Alex")
+            ))
+        );
+        // Only once, both while it's being sent and after
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 0);
+        eventually(|| reminders_settled(&pool)).await?;
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 0);
+        assert_eq!(send("reminders").await?, msg.reminders(&[]));
+        assert!(send("remind daily at 9am stretch")
+            .await?
+            .contains(" daily, starting "));
+        let id = query!("select id as \"id!\" from reminders where sent = 0")
+            .fetch_one(&pool)
+            .await?
+            .id;
+        assert!(send("reminders").await?.contains(&format!("#{id} ")));
+        query!("update reminders set due_at = unixepoch() - 1")
+            .execute(&pool)
+            .await?;
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 1);
+        assert_eq!(
+            deliveries.recv().await,
+            Some(("TEST_NUMBER".to_string(), msg.reminder("stretch")))
+        );
+        // Still waiting for tomorrow's
+        eventually(|| reminders_settled(&pool)).await?;
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 0);
+        assert_eq!(send("unremind").await?, Command::unremind.hint(&msg));
+        assert_eq!(send("unremind x").await?, Command::unremind.hint(&msg));
+        assert_eq!(
+            send(&format!("unremind {id}")).await?,
+            msg.reminder_cancelled(id)
+        );
+        assert_eq!(
+            send(&format!("unremind {id}")).await?,
+            msg.reminder_not_found(id)
+        );
+        assert_eq!(send("reminders").await?, msg.reminders(&[]));
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn reminder_retries(pool: Pool<Sqlite>) -> Result<()> {
+        let (delivered, mut deliveries) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = TestApp::new(pool.clone());
+        // Fails the first send, like when the circuit breaker is open
+        let failed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        app.state.queue = MessageQueue::start(1, u32::MAX, move |to, _| {
+            let _ = delivered.send(to);
+            let first = !failed.swap(true, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if first {
+                    Err(SendError::Unavailable.into())
+                } else {
+                    Ok(())
+                }
+            }
+        });
+        let queue = &app.state.queue;
+        app.send("TEST_NUMBER", "name Sam C.").await?;
+        app.send("TEST_NUMBER", "remind at 3pm tomorrow call Alex")
+            .await?;
+        query!("update reminders set due_at = unixepoch() - 1")
+            .execute(&pool)
+            .await?;
+        let sent = || async {
+            Ok(query!("select sent from reminders")
+                .fetch_one(&pool)
+                .await?
+                .sent)
+        };
+
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 1);
+        assert_eq!(deliveries.recv().await.as_deref(), Some("TEST_NUMBER"));
+        eventually(|| reminders_settled(&pool)).await?;
+        assert!(!sent().await?);
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 1);
+        assert_eq!(deliveries.recv().await.as_deref(), Some("TEST_NUMBER"));
+        eventually(sent).await?;
+
+        // A claim nobody finished, e.g. from before a restart, runs out eventually
+        query!(
+            "update reminders set sent = 0, claimed_at = unixepoch() - 60 where due_at < unixepoch()"
+        )
+        .execute(&pool)
+        .await?;
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 0);
+        query!("update reminders set claimed_at = unixepoch() - 60 * 60")
+            .execute(&pool)
+            .await?;
+        assert_eq!(deliver_due_reminders(&pool, queue).await?, 1);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn timezones(pool: Pool<Sqlite>) -> Result<()> {
+        let app = TestApp::new(pool.clone());
+        let send = |body: &str| app.send("TEST_NUMBER", body);
+        let msg = MessageCatalog::new(DEFAULT_LOCALE);
+        send("name Sam C.").await?;
+        assert_eq!(send("tz").await?, Command::tz.usage(&msg));
+        assert_eq!(
+            send("tz asia/tokio").await?,
+            msg.timezone_not_recognized("asia/tokio", &parse_timezone("asia/tokio").unwrap_err())
+        );
+        assert_eq!(
+            send("timezone asia/tokyo").await?,
+            msg.timezone_updated("Asia/Tokyo")
+        );
+        assert!(send("remind at 3pm tomorrow call Alex")
+            .await?
+            .contains(" 15:00 JST"));
+        let due_at = query!("select due_at from reminders")
+            .fetch_one(&pool)
+            .await?
+            .due_at;
+        assert_eq!(
+            DateTime::from_timestamp(due_at, 0)
+                .unwrap()
+                .format("%H:%M")
+                .to_string(),
+            "06:00"
+        );
+        assert!(send("reminders").await?.contains(" 15:00 JST"));
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn registration_throttling(pool: Pool<Sqlite>) -> Result<()> {
+        let mut app = TestApp::new(pool);
+        app.client_ip = Some("203.0.113.7".parse()?);
+        app.state.max_registrations_per_ip = Some(5);
+        let register = |number: &str| app.send(number, "name Sam C.");
+        for i in 0..5 {
+            assert!(register(&format!("TEST_NUMBER_{i}"))
+                .await?
+                .starts_with("Hello"));
+        }
+        assert_eq!(
+            register("TEST_NUMBER_X").await?,
+            MessageCatalog::new(DEFAULT_LOCALE).too_many_registrations()
+        );
+        Ok(())
+    }
 }
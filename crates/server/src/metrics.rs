@@ -0,0 +1,194 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    env,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+use crate::state::SharedState;
+
+// The crate shares this module's name, so it's referred to by its absolute path
+use ::metrics::{counter, histogram};
+
+// Installs the global recorder; the handle renders everything recorded so far
+pub(crate) fn install() -> anyhow::Result<PrometheusHandle> {
+    Ok(PrometheusBuilder::new().install_recorder()?)
+}
+
+pub(crate) fn record_message(command: &str) {
+    counter!("sms_messages_total", "command" => command.to_string()).increment(1);
+}
+
+pub(crate) fn record_error() {
+    counter!("sms_errors_total").increment(1);
+}
+
+pub(crate) fn record_outbound(latency: Duration) {
+    counter!("sms_outbound_total").increment(1);
+    histogram!("sms_outbound_latency_seconds").record(latency.as_secs_f64());
+}
+
+// Latency samples kept per command; older ones are dropped
+const SAMPLES_PER_COMMAND: usize = 1000;
+const RECENT: Duration = Duration::from_secs(60);
+const QUANTILES: [f64; 3] = [0.5, 0.95, 0.99];
+
+/// Rolling window of how long each command took to handle
+#[derive(Default)]
+pub(crate) struct CommandMetrics {
+    latencies: BTreeMap<String, VecDeque<(Instant, Duration)>>,
+}
+
+impl CommandMetrics {
+    pub fn record(&mut self, command: &str, latency: Duration) {
+        self.record_at(command, latency, Instant::now());
+    }
+
+    fn record_at(&mut self, command: &str, latency: Duration, now: Instant) {
+        let samples = self.latencies.entry(command.to_string()).or_default();
+        if samples.len() == SAMPLES_PER_COMMAND {
+            samples.pop_front();
+        }
+        samples.push_back((now, latency));
+    }
+
+    /// P50, P95 and P99 over every retained sample
+    pub fn percentiles(&self, command: &str) -> Option<[Duration; 3]> {
+        let mut sorted = self
+            .latencies
+            .get(command)?
+            .iter()
+            .map(|(_, latency)| *latency)
+            .collect::<Vec<_>>();
+        sorted.sort();
+        Some(QUANTILES.map(|quantile| percentile(&sorted, quantile)))
+    }
+
+    /// P95 over the last minute
+    pub fn recent_p95(&self, command: &str) -> Option<Duration> {
+        self.recent_p95_at(command, Instant::now())
+    }
+
+    fn recent_p95_at(&self, command: &str, now: Instant) -> Option<Duration> {
+        let mut sorted = self
+            .latencies
+            .get(command)?
+            .iter()
+            .filter(|(time, _)| now.duration_since(*time) < RECENT)
+            .map(|(_, latency)| *latency)
+            .collect::<Vec<_>>();
+        sorted.sort();
+        (!sorted.is_empty()).then(|| percentile(&sorted, 0.95))
+    }
+
+    // Prometheus text format, to go alongside what the recorder renders
+    fn render(&self) -> String {
+        let mut text = String::from("# TYPE sms_command_latency_seconds summary\n");
+        for command in self.latencies.keys() {
+            let Some(percentiles) = self.percentiles(command) else {
+                continue;
+            };
+            for (quantile, latency) in QUANTILES.iter().zip(percentiles) {
+                let _ = writeln!(
+                    text,
+                    "sms_command_latency_seconds{{command=\"{command}\",quantile=\"{quantile}\"}} {}",
+                    latency.as_secs_f64()
+                );
+            }
+        }
+        text
+    }
+}
+
+// Nearest-rank percentile of a non-empty sorted slice
+fn percentile(sorted: &[Duration], quantile: f64) -> Duration {
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Credentials come from METRICS_USERNAME and METRICS_PASSWORD; without them nobody gets in
+pub(crate) async fn handle_metrics(
+    Extension(state): Extension<SharedState>,
+    headers: HeaderMap,
+) -> Response {
+    let credential = |name| {
+        env::var(name)
+            .ok()
+            .filter(|value: &String| !value.is_empty())
+    };
+    let (Some(username), Some(password)) = (
+        credential("METRICS_USERNAME"),
+        credential("METRICS_PASSWORD"),
+    ) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !is_authorized(&headers, &username, &password) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Basic realm=\"metrics\"")],
+        )
+            .into_response();
+    }
+    let command_latencies = state.command_metrics.lock().unwrap().render();
+    format!("{}{command_latencies}", state.metrics_handle.render()).into_response()
+}
+
+fn is_authorized(headers: &HeaderMap, username: &str, password: &str) -> bool {
+    let expected = format!(
+        "Basic {}",
+        STANDARD.encode(format!("{username}:{password}"))
+    );
+    headers
+        .get(header::AUTHORIZATION)
+        .is_some_and(|value| value.as_bytes() == expected.as_bytes())
+}
+
+#[test]
+fn basic_auth() {
+    let mut headers = HeaderMap::new();
+    assert!(!is_authorized(&headers, "Aladdin", "open sesame"));
+    // Example from RFC 7617
+    headers.insert(
+        header::AUTHORIZATION,
+        "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==".parse().unwrap(),
+    );
+    assert!(is_authorized(&headers, "Aladdin", "open sesame"));
+    assert!(!is_authorized(&headers, "Aladdin", "closed sesame"));
+}
+
+#[test]
+fn command_latencies() {
+    let mut metrics = CommandMetrics::default();
+    assert!(metrics.percentiles("h").is_none());
+    let start = Instant::now();
+    for millis in 1..=100 {
+        metrics.record_at("h", Duration::from_millis(millis), start);
+    }
+    assert_eq!(
+        metrics.percentiles("h").unwrap(),
+        [50, 95, 99].map(Duration::from_millis)
+    );
+    assert_eq!(
+        metrics.recent_p95_at("h", start + Duration::from_secs(1)),
+        Some(Duration::from_millis(95))
+    );
+    assert_eq!(metrics.recent_p95_at("h", start + RECENT), None);
+    for _ in 0..SAMPLES_PER_COMMAND {
+        metrics.record_at("h", Duration::from_millis(7), start);
+    }
+    assert_eq!(
+        metrics.percentiles("h").unwrap(),
+        [7, 7, 7].map(Duration::from_millis)
+    );
+    assert!(metrics
+        .render()
+        .contains("sms_command_latency_seconds{command=\"h\",quantile=\"0.95\"} 0.007"));
+}
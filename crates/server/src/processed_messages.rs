@@ -0,0 +1,112 @@
+use sqlx::{query, Pool, Sqlite};
+
+// How long a MessageSid is remembered; Twilio stops retrying well before this
+const RETENTION_SECS: i64 = 24 * 60 * 60;
+
+// Twilio gives up on a webhook after 15 seconds, so an attempt still unfinished by
+// then has most likely crashed and the message can be claimed again
+const CLAIM_TIMEOUT_SECS: i64 = 15;
+
+/// Marks a message from `from` as being processed. If it already was, returns the reply
+/// sent the first time, or None if that attempt hasn't finished yet.
+pub(crate) async fn claim(
+    pool: &Pool<Sqlite>,
+    sid: &str,
+    from: &str,
+) -> sqlx::Result<Option<Option<String>>> {
+    let claimed = query!(
+        "insert into processed_messages (message_sid, from_number) values (?, ?)
+        on conflict (message_sid) do update set processed_at = unixepoch()
+        where response is null and processed_at < unixepoch() - ?",
+        sid,
+        from,
+        CLAIM_TIMEOUT_SECS
+    )
+    .execute(pool)
+    .await?
+    .rows_affected()
+        == 1;
+    if claimed {
+        return Ok(None);
+    }
+    Ok(Some(
+        query!(
+            "select response from processed_messages where message_sid = ?",
+            sid
+        )
+        .fetch_one(pool)
+        .await?
+        .response,
+    ))
+}
+
+pub(crate) async fn save_response(
+    pool: &Pool<Sqlite>,
+    sid: &str,
+    response: &str,
+) -> sqlx::Result<()> {
+    query!(
+        "update processed_messages set response = ? where message_sid = ?",
+        response,
+        sid
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Gives up a claim without saving a reply, so a retry is processed from scratch.
+/// Used when processing failed, since an error reply shouldn't be replayed.
+pub(crate) async fn release(pool: &Pool<Sqlite>, sid: &str) -> sqlx::Result<()> {
+    query!("delete from processed_messages where message_sid = ?", sid)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Returns the number of entries removed
+pub(crate) async fn cleanup(pool: &Pool<Sqlite>) -> sqlx::Result<u64> {
+    Ok(query!(
+        "delete from processed_messages where processed_at < unixepoch() - ?",
+        RETENTION_SECS
+    )
+    .execute(pool)
+    .await?
+    .rows_affected())
+}
+
+#[sqlx::test]
+async fn processed_messages(pool: Pool<Sqlite>) -> sqlx::Result<()> {
+    let from = "+15551234567";
+    assert_eq!(claim(&pool, "SM123", from).await?, None);
+    assert_eq!(claim(&pool, "SM123", from).await?, Some(None));
+    save_response(&pool, "SM123", "<Response/>").await?;
+    assert_eq!(
+        claim(&pool, "SM123", from).await?,
+        Some(Some("<Response/>".to_string()))
+    );
+    query!("update processed_messages set processed_at = processed_at - 2 * 24 * 60 * 60")
+        .execute(&pool)
+        .await?;
+    assert_eq!(cleanup(&pool).await?, 1);
+    assert_eq!(claim(&pool, "SM123", from).await?, None);
+
+    // Released claims and ones abandoned mid-processing can be claimed again
+    release(&pool, "SM123").await?;
+    assert_eq!(claim(&pool, "SM123", from).await?, None);
+    query!("update processed_messages set processed_at = processed_at - 60")
+        .execute(&pool)
+        .await?;
+    assert_eq!(claim(&pool, "SM123", from).await?, None);
+    assert_eq!(claim(&pool, "SM123", from).await?, Some(None));
+    // But finished ones never are
+    save_response(&pool, "SM123", "<Response/>").await?;
+    query!("update processed_messages set processed_at = processed_at - 60")
+        .execute(&pool)
+        .await?;
+    assert_eq!(
+        claim(&pool, "SM123", from).await?,
+        Some(Some("<Response/>".to_string()))
+    );
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tracing::{debug, error, warn};
+
+use crate::{
+    error::{env_number, BotError},
+    loggable_number,
+};
+
+/// An outbound SMS waiting for a worker
+pub(crate) struct QueuedMessage {
+    // The user whose command this is a result of, who the outbound limit applies to
+    pub origin: String,
+    pub to: String,
+    pub body: String,
+    // Told how the send went, if anyone's waiting to find out
+    pub callback: Option<oneshot::Sender<Result<(), BotError>>>,
+}
+
+/// Sends messages in the background so handlers don't wait on Twilio
+#[derive(Clone)]
+pub(crate) struct MessageQueue {
+    sender: mpsc::UnboundedSender<QueuedMessage>,
+    limiter: Arc<OutboundLimiter>,
+}
+
+// SEND_WORKERS is how many messages may be in flight at once
+pub(crate) fn send_workers() -> usize {
+    env_number("SEND_WORKERS")
+        .filter(|&workers| workers > 0)
+        .unwrap_or(4)
+}
+
+// MAX_OUTBOUND_PER_MIN is how many messages one user's commands may send per minute
+pub(crate) fn max_outbound_per_min() -> u32 {
+    env_number("MAX_OUTBOUND_PER_MIN")
+        .filter(|&limit| limit > 0)
+        .unwrap_or(30)
+}
+
+/// Token bucket per originating user. Sends past the limit aren't refused, just
+/// given a later slot, so a burst drains at the refill rate.
+struct OutboundLimiter {
+    per_minute: f64,
+    // Tokens go negative as future slots are handed out
+    buckets: std::sync::Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+impl OutboundLimiter {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute: per_minute.into(),
+            buckets: Default::default(),
+        }
+    }
+
+    /// How long a send for `origin` has to wait
+    fn delay_at(&self, origin: &str, now: Instant) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap();
+        // Idle buckets are full again, so there's no need to keep them
+        buckets.retain(|_, (tokens, updated)| {
+            *tokens + self.refill(now.duration_since(*updated)) < self.per_minute
+        });
+        let (tokens, updated) = buckets
+            .entry(origin.to_string())
+            .or_insert((self.per_minute, now));
+        *tokens = (*tokens + self.refill(now.duration_since(*updated))).min(self.per_minute) - 1.0;
+        *updated = now;
+        if *tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*tokens * 60.0 / self.per_minute)
+        }
+    }
+
+    fn refill(&self, elapsed: Duration) -> f64 {
+        elapsed.as_secs_f64() * self.per_minute / 60.0
+    }
+}
+
+impl MessageQueue {
+    /// Spawns `workers` tasks that pass each queued message to `deliver`
+    pub fn start<F, Fut>(workers: usize, max_outbound_per_min: u32, deliver: F) -> Self
+    where
+        F: Fn(String, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), BotError>> + Send,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel::<QueuedMessage>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let deliver = Arc::new(deliver);
+        for _ in 0..workers {
+            let receiver = receiver.clone();
+            let deliver = deliver.clone();
+            tokio::spawn(async move {
+                loop {
+                    // Only hold the lock while waiting, not while sending
+                    let Some(message) = receiver.lock().await.recv().await else {
+                        break;
+                    };
+                    let to_logged = loggable_number(&message.to);
+                    let result = deliver(message.to, message.body).await;
+                    if let Err(error) = &result {
+                        error!("Failed to send queued message to {to_logged}: {error:?}");
+                    }
+                    if let Some(callback) = message.callback {
+                        let _ = callback.send(result);
+                    }
+                }
+            });
+        }
+        Self {
+            sender,
+            limiter: Arc::new(OutboundLimiter::new(max_outbound_per_min)),
+        }
+    }
+
+    pub fn enqueue(&self, message: QueuedMessage) {
+        let delay = self.limiter.delay_at(&message.origin, Instant::now());
+        if delay.is_zero() {
+            send_to_workers(&self.sender, message);
+            return;
+        }
+        debug!(
+            "Deferring message from {} by {delay:?}",
+            loggable_number(&message.origin)
+        );
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            send_to_workers(&sender, message);
+        });
+    }
+}
+
+fn send_to_workers(sender: &mpsc::UnboundedSender<QueuedMessage>, message: QueuedMessage) {
+    if sender.send(message).is_err() {
+        warn!("Message queue is closed, dropping message");
+    }
+}
+
+#[tokio::test]
+async fn queue() {
+    let delivered = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let queue = MessageQueue::start(2, u32::MAX, {
+        let delivered = delivered.clone();
+        move |to, body| {
+            let delivered = delivered.clone();
+            async move {
+                delivered.lock().unwrap().push((to, body));
+                Ok(())
+            }
+        }
+    });
+    // Stands in for a handler that returns as soon as it has queued its messages
+    let handler = |queue: &MessageQueue| {
+        ["first", "second"].map(|body| {
+            let (callback, done) = oneshot::channel();
+            queue.enqueue(QueuedMessage {
+                origin: "+15557654321".to_string(),
+                to: "+15551234567".to_string(),
+                body: body.to_string(),
+                callback: Some(callback),
+            });
+            done
+        })
+    };
+    for done in handler(&queue) {
+        assert!(done.await.unwrap().is_ok());
+    }
+    let mut delivered = delivered.lock().unwrap().clone();
+    delivered.sort();
+    assert_eq!(
+        delivered,
+        [
+            ("+15551234567".to_string(), "first".to_string()),
+            ("+15551234567".to_string(), "second".to_string())
+        ]
+    );
+}
+
+#[test]
+fn outbound_limit() {
+    let limiter = OutboundLimiter::new(30);
+    let start = Instant::now();
+    for _ in 0..30 {
+        assert_eq!(limiter.delay_at("A", start), Duration::ZERO);
+    }
+    // Refills at one every two seconds, and each deferred send takes the next slot
+    assert_eq!(limiter.delay_at("A", start), Duration::from_secs(2));
+    assert_eq!(limiter.delay_at("A", start), Duration::from_secs(4));
+    assert_eq!(limiter.delay_at("B", start), Duration::ZERO);
+    assert_eq!(
+        limiter.delay_at("A", start + Duration::from_secs(1)),
+        Duration::from_secs(5)
+    );
+    // Back to full after a quiet minute, and forgotten
+    let later = start + Duration::from_secs(120);
+    assert_eq!(limiter.delay_at("B", later), Duration::ZERO);
+    assert!(!limiter.buckets.lock().unwrap().contains_key("A"));
+    assert_eq!(limiter.delay_at("A", later), Duration::ZERO);
+}
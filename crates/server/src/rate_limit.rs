@@ -0,0 +1,72 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::error::env_number;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Sliding-window limit on how many messages each number may send per minute
+pub(crate) struct RateLimiter {
+    limit_per_minute: usize,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit_per_minute: usize) -> Self {
+        Self {
+            limit_per_minute,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(env_number("RATE_LIMIT_PER_MINUTE").unwrap_or(10))
+    }
+
+    /// Records a message from `number`, returning false if it is over the limit
+    pub fn check(&self, number: &str) -> bool {
+        self.check_at(number, Instant::now())
+    }
+
+    fn check_at(&self, number: &str, now: Instant) -> bool {
+        let mut hits = self.hits.lock().unwrap();
+        let times = hits.entry(number.to_string()).or_default();
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= WINDOW)
+        {
+            times.pop_front();
+        }
+        if times.len() >= self.limit_per_minute {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+
+    /// Forgets numbers that haven't sent anything within the window
+    pub fn cleanup(&self) {
+        let now = Instant::now();
+        self.hits.lock().unwrap().retain(|_, times| {
+            times
+                .back()
+                .is_some_and(|time| now.duration_since(*time) < WINDOW)
+        });
+    }
+}
+
+#[test]
+fn rate_limit() {
+    let limiter = RateLimiter::new(2);
+    let start = Instant::now();
+    assert!(limiter.check_at("A", start));
+    assert!(limiter.check_at("A", start + Duration::from_secs(10)));
+    assert!(!limiter.check_at("A", start + Duration::from_secs(20)));
+    assert!(limiter.check_at("B", start + Duration::from_secs(20)));
+    // the first message has left the window
+    assert!(limiter.check_at("A", start + Duration::from_secs(61)));
+    assert!(!limiter.check_at("A", start + Duration::from_secs(62)));
+}
@@ -0,0 +1,353 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Days, Months, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::{query, Pool, Sqlite};
+use tokio::sync::oneshot;
+use tracing::{error, warn};
+
+use crate::{
+    error::BotError,
+    i18n::MessageCatalog,
+    queue::{MessageQueue, QueuedMessage},
+    timezone::user_timezone,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// Days and months are counted on the local clock, so a daily reminder stays put across DST changes
+impl Recurrence {
+    fn advance(self, due_at: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        match self {
+            Self::Daily => due_at.checked_add_days(Days::new(1)),
+            Self::Weekly => due_at.checked_add_days(Days::new(7)),
+            Self::Monthly => due_at.checked_add_months(Months::new(1)),
+        }
+    }
+
+    // The first due time after `now`, so reminders missed while we were down aren't sent in a burst
+    fn next_after(self, mut due_at: DateTime<Tz>, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        loop {
+            due_at = self.advance(due_at)?;
+            if due_at > now {
+                return Some(due_at);
+            }
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            "monthly" => Ok(Self::Monthly),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct ParsedReminder {
+    pub due_at: DateTime<Utc>,
+    pub recurrence: Option<Recurrence>,
+    pub message: String,
+}
+
+/// Parses "[daily|weekly|monthly] at <time> [today|tomorrow] <message>", with the time
+/// on the clock of `now`'s time zone. Without a day, the next time that time comes
+/// around is used. Recurring reminders may also leave out the time, in which case
+/// the first one is an interval from now.
+pub(crate) fn parse_reminder(words: &[&str], now: DateTime<Tz>) -> Option<ParsedReminder> {
+    let (recurrence, words) = match words {
+        [recurrence, rest @ ..] => match recurrence.parse::<Recurrence>() {
+            Ok(recurrence) => (Some(recurrence), rest),
+            Err(()) => (None, words),
+        },
+        [] => return None,
+    };
+    if let Some(recurrence) = recurrence {
+        if words
+            .first()
+            .is_some_and(|word| !word.eq_ignore_ascii_case("at"))
+        {
+            return Some(ParsedReminder {
+                due_at: recurrence.advance(now)?.to_utc(),
+                recurrence: Some(recurrence),
+                message: words.join(" "),
+            });
+        }
+    }
+    let (due_at, message) = parse_one_off(words, now)?;
+    Some(ParsedReminder {
+        due_at: due_at.to_utc(),
+        recurrence,
+        message,
+    })
+}
+
+fn parse_one_off(words: &[&str], now: DateTime<Tz>) -> Option<(DateTime<Tz>, String)> {
+    let [at, time, rest @ ..] = words else {
+        return None;
+    };
+    if !at.eq_ignore_ascii_case("at") {
+        return None;
+    }
+    let time = parse_time(time)?;
+    let (day, message) = match rest {
+        [day, message @ ..] if day.eq_ignore_ascii_case("today") => (Some(0), message),
+        [day, message @ ..] if day.eq_ignore_ascii_case("tomorrow") => (Some(1), message),
+        message => (None, message),
+    };
+    if message.is_empty() {
+        return None;
+    }
+    // A time skipped by a DST change doesn't exist that day
+    let on = |date: NaiveDate| {
+        now.timezone()
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+    };
+    let today = now.date_naive();
+    let due_at = match day {
+        Some(days) => on(today.checked_add_days(Days::new(days))?)?,
+        None => match on(today) {
+            Some(due_at) if due_at > now => due_at,
+            _ => on(today.succ_opt()?)?,
+        },
+    };
+    (due_at > now).then(|| (due_at, message.join(" ")))
+}
+
+// Accepts 24-hour times like "15:30" and 12-hour ones like "3pm" or "3:30pm"
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    let text = text.to_lowercase();
+    let (clock, pm) = if let Some(clock) = text.strip_suffix("pm") {
+        (clock, Some(true))
+    } else if let Some(clock) = text.strip_suffix("am") {
+        (clock, Some(false))
+    } else {
+        (text.as_str(), None)
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(true) => hour % 12 + 12,
+        Some(false) => hour % 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+// A claim this old belongs to a send that never reported back, e.g. because the server
+// stopped with it still queued. Long enough to cover the outbound limiter's deferrals.
+const CLAIM_TIMEOUT_SECS: i64 = 10 * 60;
+
+/// Queues every reminder that has come due, returning how many there were. Each is claimed
+/// while it's being sent, and only marked sent (or moved to its next time) once it has been.
+pub(crate) async fn deliver_due_reminders(
+    pool: &Pool<Sqlite>,
+    queue: &MessageQueue,
+) -> Result<usize, BotError> {
+    let due = query!(
+        "update reminders set claimed_at = unixepoch()
+        where due_at <= unixepoch() and sent = 0
+        and (claimed_at is null or claimed_at < unixepoch() - ?)
+        returning id as \"id!\", user_number, message, due_at, recurrence,
+        (select locale from users where number = user_number) as \"locale!: String\",
+        (select timezone from users where number = user_number) as \"timezone!: String\"",
+        CLAIM_TIMEOUT_SECS
+    )
+    .fetch_all(pool)
+    .await?;
+    let now = Utc::now();
+    for reminder in &due {
+        let timezone = user_timezone(&reminder.timezone);
+        let next_due = reminder
+            .recurrence
+            .as_deref()
+            .and_then(|recurrence| recurrence.parse::<Recurrence>().ok())
+            .zip(DateTime::from_timestamp(reminder.due_at, 0))
+            .and_then(|(recurrence, due_at)| {
+                recurrence.next_after(
+                    due_at.with_timezone(&timezone),
+                    now.with_timezone(&timezone),
+                )
+            });
+        let (callback, delivered) = oneshot::channel();
+        queue.enqueue(QueuedMessage {
+            origin: reminder.user_number.clone(),
+            to: reminder.user_number.clone(),
+            body: MessageCatalog::new(&reminder.locale).reminder(&reminder.message),
+            callback: Some(callback),
+        });
+        tokio::spawn(finish_reminder(
+            pool.clone(),
+            reminder.id,
+            next_due.map(|next_due| next_due.timestamp()),
+            delivered,
+        ));
+    }
+    Ok(due.len())
+}
+
+// Recurring reminders move on to their next time and one-offs are done. If the send
+// failed, the claim is dropped so the next pass tries again.
+async fn finish_reminder(
+    pool: Pool<Sqlite>,
+    id: i64,
+    next_due: Option<i64>,
+    delivered: oneshot::Receiver<Result<(), BotError>>,
+) {
+    let result = match delivered.await {
+        Ok(Ok(())) => match next_due {
+            Some(next_due) => query!(
+                "update reminders set due_at = ?, claimed_at = null where id = ?",
+                next_due,
+                id
+            )
+            .execute(&pool)
+            .await
+            .map(|_| ()),
+            None => query!(
+                "update reminders set sent = 1, claimed_at = null where id = ?",
+                id
+            )
+            .execute(&pool)
+            .await
+            .map(|_| ()),
+        },
+        Ok(Err(_)) => {
+            // The queue already logged why
+            warn!("Reminder #{id} wasn't delivered, will retry");
+            query!("update reminders set claimed_at = null where id = ?", id)
+                .execute(&pool)
+                .await
+                .map(|_| ())
+        }
+        // The queue went away with it; the claim expires on its own
+        Err(_) => return,
+    };
+    if let Err(error) = result {
+        error!("Failed to update reminder #{id} after sending: {error:?}");
+    }
+}
+
+#[test]
+fn reminder_parsing() {
+    let now = DateTime::parse_from_rfc3339("2026-10-14T12:00:00Z")
+        .unwrap()
+        .with_timezone(&Tz::UTC);
+    let parse = |text: &str| {
+        parse_reminder(&text.split(' ').collect::<Vec<_>>(), now)
+            .map(|reminder| (reminder.due_at.to_rfc3339(), reminder.message))
+    };
+    assert_eq!(
+        parse("at 3pm tomorrow call Alex"),
+        Some((
+            "2026-10-15T15:00:00+00:00".to_string(),
+            "call Alex".to_string()
+        ))
+    );
+    assert_eq!(
+        parse("at 15:30 stretch"),
+        Some((
+            "2026-10-14T15:30:00+00:00".to_string(),
+            "stretch".to_string()
+        ))
+    );
+    // Already passed today, so it's tomorrow
+    assert_eq!(
+        parse("AT 9:15am stretch"),
+        Some((
+            "2026-10-15T09:15:00+00:00".to_string(),
+            "stretch".to_string()
+        ))
+    );
+    assert_eq!(
+        parse("at 12am today sleep"),
+        None,
+        "midnight today has passed"
+    );
+    assert_eq!(parse("at 3pm"), None);
+    assert_eq!(parse("at 3pm tomorrow"), None);
+    assert_eq!(parse("at 13pm stretch"), None);
+    assert_eq!(parse("at 25:00 stretch"), None);
+    assert_eq!(parse("in 5 minutes stretch"), None);
+    assert_eq!(
+        parse_reminder(&["daily", "at", "9am", "stretch"], now),
+        Some(ParsedReminder {
+            due_at: DateTime::parse_from_rfc3339("2026-10-15T09:00:00Z")
+                .unwrap()
+                .to_utc(),
+            recurrence: Some(Recurrence::Daily),
+            message: "stretch".to_string(),
+        })
+    );
+    assert_eq!(
+        parse("weekly water the plants"),
+        Some((
+            "2026-10-21T12:00:00+00:00".to_string(),
+            "water the plants".to_string()
+        ))
+    );
+    assert_eq!(parse("monthly"), None);
+    // 8am in New York, so 3pm is still to come today
+    assert_eq!(
+        parse_reminder(
+            &["at", "3pm", "stretch"],
+            now.with_timezone(&Tz::America__New_York)
+        )
+        .map(|reminder| reminder.due_at.to_rfc3339()),
+        Some("2026-10-14T19:00:00+00:00".to_string())
+    );
+}
+
+#[test]
+fn recurrence() {
+    let at = |text: &str| {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Tz::UTC)
+    };
+    assert_eq!(
+        Recurrence::Monthly.next_after(at("2026-01-31T09:00:00Z"), at("2026-01-31T09:00:00Z")),
+        Some(at("2026-02-28T09:00:00Z"))
+    );
+    // Skips the ones missed while the server was down
+    assert_eq!(
+        Recurrence::Daily.next_after(at("2026-10-10T09:00:00Z"), at("2026-10-14T12:00:00Z")),
+        Some(at("2026-10-15T09:00:00Z"))
+    );
+    // Still 9am in New York after the clocks go back on November 1st
+    let new_york = |text: &str| at(text).with_timezone(&Tz::America__New_York);
+    assert_eq!(
+        Recurrence::Daily.next_after(
+            new_york("2026-10-31T13:00:00Z"),
+            new_york("2026-10-31T13:00:00Z")
+        ),
+        Some(new_york("2026-11-01T14:00:00Z"))
+    );
+    assert_eq!("WEEKLY".parse(), Ok(Recurrence::Weekly));
+    assert_eq!(Recurrence::Weekly.to_string(), "weekly");
+}
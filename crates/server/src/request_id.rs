@@ -0,0 +1,42 @@
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+/// Identifier used to correlate our logs with Twilio's for a single webhook
+#[derive(Clone, Debug)]
+pub(crate) struct RequestId(pub String);
+
+pub(crate) async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let id = request_id(request.headers());
+    request.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert("x-request-id", value);
+    }
+    response
+}
+
+// The signature is unique per webhook, so its tail identifies it in Twilio's logs too
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get("x-twilio-signature")
+        .and_then(|signature| signature.to_str().ok())
+        .and_then(|signature| signature.get(signature.len().checked_sub(8)?..))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+#[test]
+fn request_ids() {
+    let mut headers = HeaderMap::new();
+    assert!(Uuid::parse_str(&request_id(&headers)).is_ok());
+    headers.insert(
+        "x-twilio-signature",
+        "RSOYDt4T1cUTdK1PDd93/VVr8B8=".parse().unwrap(),
+    );
+    assert_eq!(request_id(&headers), "/VVr8B8=");
+}
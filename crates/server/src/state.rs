@@ -0,0 +1,44 @@
+use std::sync::{Arc, Mutex};
+
+use metrics_exporter_prometheus::PrometheusHandle;
+use openapi::apis::configuration::Configuration;
+use sqlx::{Pool, Sqlite};
+
+use crate::{bot::Bots, metrics::CommandMetrics, queue::MessageQueue, rate_limit::RateLimiter};
+
+/// Everything the handlers share, so it can go behind a single Extension
+pub(crate) struct AppState {
+    pub pool: Pool<Sqlite>,
+    pub twilio_config: Arc<Configuration>,
+    pub queue: MessageQueue,
+    pub bots: Bots,
+    // Numbers allowed to use admin commands
+    pub admins: Vec<String>,
+    pub limiter: RateLimiter,
+    // New users allowed per client IP per 24 hours; None doesn't throttle
+    pub max_registrations_per_ip: Option<i32>,
+    pub command_metrics: Mutex<CommandMetrics>,
+    pub metrics_handle: PrometheusHandle,
+}
+
+pub(crate) type SharedState = Arc<AppState>;
+
+#[cfg(test)]
+impl AppState {
+    /// Nothing is rate limited and nothing sent actually goes anywhere
+    pub fn test(pool: Pool<Sqlite>) -> Self {
+        Self {
+            pool,
+            twilio_config: Default::default(),
+            queue: MessageQueue::start(1, u32::MAX, |_, _| async { Ok(()) }),
+            bots: Bots::new(),
+            admins: Vec::new(),
+            limiter: RateLimiter::new(usize::MAX),
+            max_registrations_per_ip: None,
+            command_metrics: Mutex::default(),
+            metrics_handle: metrics_exporter_prometheus::PrometheusBuilder::new()
+                .build_recorder()
+                .handle(),
+        }
+    }
+}
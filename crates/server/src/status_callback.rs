@@ -0,0 +1,81 @@
+use axum::{http::StatusCode, Extension, Form};
+use serde::Deserialize;
+use sqlx::query;
+use tracing::{debug, error, warn};
+
+use crate::{loggable_number, state::SharedState};
+
+// field names must be exact (including case) to match API
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+pub(crate) struct MessageStatus {
+    MessageSid: String,
+    MessageStatus: String,
+    To: String,
+    ErrorCode: Option<String>,
+}
+
+// Handler for Twilio delivery status callbacks on messages from `send()`
+pub(crate) async fn handle_status_callback(
+    Extension(state): Extension<SharedState>,
+    Form(status): Form<MessageStatus>,
+) -> StatusCode {
+    let MessageStatus {
+        MessageSid: sid,
+        MessageStatus: status,
+        To: to,
+        ErrorCode: error_code,
+    } = status;
+    if let Some(error_code) = &error_code {
+        warn!(
+            "Message {sid} to {} is {status} with error code {error_code}",
+            loggable_number(&to)
+        );
+    } else {
+        debug!("Message {sid} is {status}");
+    }
+    let result = query!(
+        "insert into message_status (message_sid, status, error_code) values (?, ?, ?)
+        on conflict (message_sid) do update
+        set status = excluded.status, error_code = excluded.error_code, updated_at = unixepoch()",
+        sid,
+        status,
+        error_code
+    )
+    .execute(&state.pool)
+    .await;
+    match result {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(error) => {
+            error!("Error storing message status: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[sqlx::test]
+async fn status_callback(pool: sqlx::Pool<sqlx::Sqlite>) -> sqlx::Result<()> {
+    let state = std::sync::Arc::new(crate::state::AppState::test(pool.clone()));
+    let callback = |status: &str, error_code: Option<&str>| {
+        handle_status_callback(
+            Extension(state.clone()),
+            Form(MessageStatus {
+                MessageSid: "SM123".to_string(),
+                MessageStatus: status.to_string(),
+                To: "+15551234567".to_string(),
+                ErrorCode: error_code.map(str::to_string),
+            }),
+        )
+    };
+    assert_eq!(callback("sent", None).await, StatusCode::NO_CONTENT);
+    assert_eq!(
+        callback("failed", Some("30003")).await,
+        StatusCode::NO_CONTENT
+    );
+    let row = query!("select status, error_code from message_status where message_sid = 'SM123'")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(row.status, "failed");
+    assert_eq!(row.error_code.as_deref(), Some("30003"));
+    Ok(())
+}
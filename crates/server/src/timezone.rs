@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+
+// How many names to offer when one isn't recognized
+const SUGGESTIONS: usize = 3;
+
+/// Looks up an IANA name like "America/New_York", ignoring case.
+/// If there's no such zone, returns the closest names instead.
+pub(crate) fn parse_timezone(name: &str) -> Result<Tz, Vec<&'static str>> {
+    if let Some(timezone) = TZ_VARIANTS
+        .iter()
+        .find(|timezone| timezone.name().eq_ignore_ascii_case(name))
+    {
+        return Ok(*timezone);
+    }
+    let name = name.to_lowercase();
+    let mut ranked = TZ_VARIANTS
+        .iter()
+        .map(|timezone| (distance(&name, timezone.name()), timezone.name()))
+        .collect::<Vec<_>>();
+    ranked.sort();
+    Err(ranked
+        .into_iter()
+        .take(SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect())
+}
+
+// The stored name was validated when it was set, so this only falls back for old rows
+pub(crate) fn user_timezone(name: &str) -> Tz {
+    name.parse().unwrap_or(Tz::UTC)
+}
+
+pub(crate) fn format_time(time: DateTime<Utc>, timezone: Tz) -> String {
+    time.with_timezone(&timezone)
+        .format("%Y-%m-%d %H:%M %Z")
+        .to_string()
+}
+
+// Also compared against just the city, so "tokio" finds Asia/Tokyo
+fn distance(name: &str, candidate: &str) -> usize {
+    let candidate = candidate.to_lowercase();
+    let city = candidate.rsplit('/').next().unwrap_or(&candidate);
+    levenshtein(name, &candidate).min(levenshtein(name, city))
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[test]
+fn timezones() {
+    assert_eq!(
+        parse_timezone("America/New_York"),
+        Ok(Tz::America__New_York)
+    );
+    assert_eq!(parse_timezone("europe/madrid"), Ok(Tz::Europe__Madrid));
+    assert_eq!(parse_timezone("UTC"), Ok(Tz::UTC));
+    assert_eq!(parse_timezone("tokio").unwrap_err()[0], "Asia/Tokyo");
+    assert!(parse_timezone("America/New_Yrok")
+        .unwrap_err()
+        .contains(&"America/New_York"));
+    assert_eq!(
+        parse_timezone("Mars/Olympus").unwrap_err().len(),
+        SUGGESTIONS
+    );
+    assert_eq!(user_timezone("Not/A_Zone"), Tz::UTC);
+    let time = DateTime::parse_from_rfc3339("2026-01-15T20:00:00Z")
+        .unwrap()
+        .to_utc();
+    assert_eq!(format_time(time, Tz::UTC), "2026-01-15 20:00 UTC");
+    assert_eq!(
+        format_time(time, Tz::America__New_York),
+        "2026-01-15 15:00 EST"
+    );
+}
@@ -0,0 +1,67 @@
+// Builds TwiML (https://www.twilio.com/docs/messaging/twiml) replies to incoming messages
+#[derive(Default)]
+pub(crate) struct ResponseBuilder {
+    messages: Vec<String>,
+}
+
+impl ResponseBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn message(mut self, text: &str) -> Self {
+        self.messages
+            .push(format!("<Message>{}</Message>", escape(text)));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn message_with_media(mut self, text: &str, media_url: &str) -> Self {
+        self.messages.push(format!(
+            "<Message><Body>{}</Body><Media>{}</Media></Message>",
+            escape(text),
+            escape(media_url)
+        ));
+        self
+    }
+
+    pub fn build(self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Response>{}</Response>",
+            self.messages.concat()
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[test]
+fn twiml() {
+    assert_eq!(
+        ResponseBuilder::new().message("1 < 2 & \"3\"").build(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Response>\
+        <Message>1 &lt; 2 &amp; &quot;3&quot;</Message>\
+        </Response>"
+    );
+    assert_eq!(
+        ResponseBuilder::new()
+            .message_with_media("look", "https://example.com/a?b=1&c=2")
+            .build(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Response>\
+        <Message><Body>look</Body><Media>https://example.com/a?b=1&amp;c=2</Media></Message>\
+        </Response>"
+    );
+}